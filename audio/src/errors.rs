@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AudioError {
+    #[error("Failed to read {}", path.display())]
+    ReadFailure {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[cfg(feature = "rodio")]
+    #[error("Failed to decode {}", path.display())]
+    DecodeFailure {
+        source: rodio::decoder::DecoderError,
+        path: PathBuf,
+    },
+}
@@ -0,0 +1,162 @@
+//! Plays sound clips, one-shot or looped.
+//!
+//! If there is no audio device, or the crate is built without the `rodio`
+//! feature, everything still works but nothing is heard.
+
+pub mod errors;
+
+#[cfg(feature = "rodio")]
+mod rodio_backend;
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::errors::AudioError;
+
+/// A loaded sound file. WAV and OGG Vorbis are supported.
+#[derive(Clone)]
+pub struct Clip {
+    data: Arc<[u8]>,
+}
+
+/// Identifies a looping sound, so it can be changed or stopped later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LoopId(u64);
+
+enum Backend {
+    #[cfg(feature = "rodio")]
+    Rodio(rodio_backend::RodioBackend),
+    Null,
+}
+
+pub struct Audio {
+    backend: Backend,
+    next_loop_id: u64,
+}
+
+impl Audio {
+    /// Plays through the default output device, or silently if there is none.
+    pub fn new() -> Self {
+        #[cfg(feature = "rodio")]
+        {
+            if let Ok(backend) = rodio_backend::RodioBackend::new() {
+                return Audio::with_backend(Backend::Rodio(backend));
+            }
+        }
+
+        Audio::null()
+    }
+
+    /// Never makes a sound. Useful for tests and headless runs.
+    pub fn null() -> Self {
+        Audio::with_backend(Backend::Null)
+    }
+
+    fn with_backend(backend: Backend) -> Self {
+        Audio {
+            backend,
+            next_loop_id: 0,
+        }
+    }
+
+    /// True if sounds are actually heard.
+    pub fn is_audible(&self) -> bool {
+        !matches!(self.backend, Backend::Null)
+    }
+
+    /// Loads a clip. The file is only decoded when the clip is played,
+    /// but it is checked here so bad files are found early.
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<Clip, AudioError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|err| AudioError::ReadFailure {
+            source: err,
+            path: path.to_path_buf(),
+        })?;
+        let clip = Clip { data: data.into() };
+
+        #[cfg(feature = "rodio")]
+        rodio_backend::check(&clip).map_err(|err| AudioError::DecodeFailure {
+            source: err,
+            path: path.to_path_buf(),
+        })?;
+
+        Ok(clip)
+    }
+
+    /// Scales the volume of every sound, including loops that are already playing.
+    pub fn set_master_volume(&mut self, volume: f32) {
+        match self.backend {
+            #[cfg(feature = "rodio")]
+            Backend::Rodio(ref mut backend) => backend.set_master_volume(volume),
+            Backend::Null => (),
+        }
+    }
+
+    /// Plays clip once.
+    pub fn play(&mut self, clip: &Clip, volume: f32) {
+        match self.backend {
+            #[cfg(feature = "rodio")]
+            Backend::Rodio(ref mut backend) => backend.play(clip, volume),
+            Backend::Null => (),
+        }
+    }
+
+    /// Plays clip over and over until it is stopped.
+    pub fn play_looped(&mut self, clip: &Clip, volume: f32) -> LoopId {
+        let id = LoopId(self.next_loop_id);
+        self.next_loop_id += 1;
+
+        match self.backend {
+            #[cfg(feature = "rodio")]
+            Backend::Rodio(ref mut backend) => backend.play_looped(id, clip, volume),
+            Backend::Null => (),
+        }
+
+        id
+    }
+
+    pub fn set_loop_volume(&mut self, id: LoopId, volume: f32) {
+        match self.backend {
+            #[cfg(feature = "rodio")]
+            Backend::Rodio(ref mut backend) => backend.set_loop_volume(id, volume),
+            Backend::Null => (),
+        }
+    }
+
+    pub fn stop_loop(&mut self, id: LoopId) {
+        match self.backend {
+            #[cfg(feature = "rodio")]
+            Backend::Rodio(ref mut backend) => backend.stop_loop(id),
+            Backend::Null => (),
+        }
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Audio::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Audio;
+
+    #[test]
+    fn null_audio_accepts_everything() {
+        let mut audio = Audio::null();
+        assert!(!audio.is_audible());
+
+        assert!(audio.load("no/such/clip.wav").is_err());
+
+        let clip = super::Clip {
+            data: vec![0u8; 16].into(),
+        };
+        audio.set_master_volume(0.5);
+        audio.play(&clip, 1.0);
+        let id = audio.play_looped(&clip, 1.0);
+        audio.set_loop_volume(id, 0.25);
+        audio.stop_loop(id);
+    }
+}
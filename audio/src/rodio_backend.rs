@@ -0,0 +1,87 @@
+use rodio::decoder::DecoderError;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crate::{Clip, LoopId};
+
+pub(crate) struct RodioBackend {
+    // The stream stops playing when it is dropped, so it must be kept.
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+
+    master_volume: f32,
+    // Sinks of the looping sounds, along with their own volumes.
+    loops: HashMap<LoopId, (Sink, f32)>,
+}
+
+impl RodioBackend {
+    pub(crate) fn new() -> Result<Self, rodio::StreamError> {
+        let (stream, handle) = OutputStream::try_default()?;
+
+        Ok(RodioBackend {
+            _stream: stream,
+            handle,
+
+            master_volume: 1.0,
+            loops: HashMap::new(),
+        })
+    }
+
+    pub(crate) fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume;
+        for (sink, loop_volume) in self.loops.values() {
+            sink.set_volume(loop_volume * volume);
+        }
+    }
+
+    pub(crate) fn play(&mut self, clip: &Clip, volume: f32) {
+        if let Some(sink) = self.create_sink(clip, volume * self.master_volume, false) {
+            sink.detach();
+        }
+    }
+
+    pub(crate) fn play_looped(&mut self, id: LoopId, clip: &Clip, volume: f32) {
+        if let Some(sink) = self.create_sink(clip, volume * self.master_volume, true) {
+            self.loops.insert(id, (sink, volume));
+        }
+    }
+
+    pub(crate) fn set_loop_volume(&mut self, id: LoopId, volume: f32) {
+        if let Some((sink, loop_volume)) = self.loops.get_mut(&id) {
+            *loop_volume = volume;
+            sink.set_volume(volume * self.master_volume);
+        }
+    }
+
+    pub(crate) fn stop_loop(&mut self, id: LoopId) {
+        if let Some((sink, _)) = self.loops.remove(&id) {
+            sink.stop();
+        }
+    }
+
+    // Playback failures are not worth interrupting the game for, so the sound is just skipped.
+    fn create_sink(&self, clip: &Clip, volume: f32, looped: bool) -> Option<Sink> {
+        let decoder = decode(clip).ok()?;
+        let sink = Sink::try_new(&self.handle).ok()?;
+        sink.set_volume(volume);
+
+        if looped {
+            sink.append(decoder.repeat_infinite());
+        } else {
+            sink.append(decoder);
+        }
+
+        Some(sink)
+    }
+}
+
+fn decode(clip: &Clip) -> Result<Decoder<Cursor<Arc<[u8]>>>, DecoderError> {
+    Decoder::new(Cursor::new(clip.data.clone()))
+}
+
+/// Checks that clip can be decoded.
+pub(crate) fn check(clip: &Clip) -> Result<(), DecoderError> {
+    decode(clip).map(|_| ())
+}
@@ -11,6 +11,7 @@ use std::f32;
 use std::time::Duration;
 
 use graphics::{
+    blend::BlendMode,
     color::Color,
     events::{Event, Key},
     screen::{Screen, ScreenCallbacks, ScreenRender, ScreenRunner},
@@ -72,8 +73,12 @@ impl App<'_, '_> {
 }
 
 impl ScreenCallbacks for App<'_, '_> {
-    fn handle_event(&mut self, _screen: &mut Screen, event: Event) {
+    fn handle_event(&mut self, screen: &mut Screen, event: Event) {
         match event {
+            Event::KeyPress {
+                key: Key::Escape,
+                down: true,
+            } => screen.exit(),
             Event::KeyPress {
                 key: Key::R,
                 down: true,
@@ -139,7 +144,7 @@ impl ScreenCallbacks for App<'_, '_> {
                     UnitComplex::identity(),
                     1.0,
                 );
-                screen_render.draw_shape(&transform, *color, &self.ball_shape);
+                screen_render.draw_shape(&transform, *color, BlendMode::Opaque, &self.ball_shape);
             }
         }
 
@@ -149,7 +154,12 @@ impl ScreenCallbacks for App<'_, '_> {
                 UnitComplex::identity(),
                 1.0,
             );
-            screen_render.draw_shape(&transform, self.ball_color, &self.ball_shape);
+            screen_render.draw_shape(
+                &transform,
+                self.ball_color,
+                BlendMode::Opaque,
+                &self.ball_shape,
+            );
         }
     }
 }
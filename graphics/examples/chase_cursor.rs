@@ -1,3 +1,4 @@
+use graphics::blend::BlendMode;
 use graphics::{color, events, model, screen};
 
 use anyhow::Result;
@@ -34,13 +35,15 @@ impl App {
             shape.clone(),
             chase_color,
             Similarity2::from_scaling(chase_scale),
-        );
+        )
+        .with_blend(BlendMode::Alpha);
         let follow_color = color::Color::new(1.0, 1.0, 0.0, 0.5);
         let follow_model = model::Model::new(
             shape.clone(),
             follow_color,
             Similarity2::from_scaling(follow_scale),
-        );
+        )
+        .with_blend(BlendMode::Alpha);
         let colors = vec![
             color::Color::new(1.0, 0.0, 0.0, 1.0),
             color::Color::new(0.0, 1.0, 0.0, 1.0),
@@ -76,8 +79,12 @@ impl App {
 }
 
 impl screen::ScreenCallbacks for App {
-    fn handle_event(&mut self, _screen: &mut screen::Screen, event: events::Event) {
+    fn handle_event(&mut self, screen: &mut screen::Screen, event: events::Event) {
         match event {
+            events::Event::KeyPress {
+                key: events::Key::Escape,
+                down: true,
+            } => screen.exit(),
             events::Event::Resize { mouse_pos } => {
                 self.follow_model.transform =
                     Similarity2::new(mouse_pos.coords, 0.0f32, self.follow_scale);
@@ -0,0 +1,65 @@
+use wgpu::{BlendFactor, BlendOperation, BlendState};
+
+/// How a shape's color is combined with what has already been drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Replaces what is underneath. Alpha is ignored.
+    Opaque,
+    /// Mixes with what is underneath by alpha.
+    Alpha,
+    /// Adds to what is underneath, scaled by alpha. Good for glows and sparks.
+    Additive,
+}
+
+impl BlendMode {
+    /// Every mode, in the order that they are drawn.
+    /// Opaque shapes go first, so translucent ones are blended over them.
+    pub(crate) const ALL: [BlendMode; 3] =
+        [BlendMode::Opaque, BlendMode::Alpha, BlendMode::Additive];
+
+    pub(crate) fn index(self) -> usize {
+        match self {
+            BlendMode::Opaque => 0,
+            BlendMode::Alpha => 1,
+            BlendMode::Additive => 2,
+        }
+    }
+
+    pub(crate) fn color_blend(self) -> BlendState {
+        match self {
+            BlendMode::Opaque => BlendState::REPLACE,
+            BlendMode::Alpha => BlendState {
+                src_factor: BlendFactor::SrcAlpha,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+            BlendMode::Additive => BlendState {
+                src_factor: BlendFactor::SrcAlpha,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            },
+        }
+    }
+
+    pub(crate) fn alpha_blend(self) -> BlendState {
+        match self {
+            BlendMode::Opaque => BlendState::REPLACE,
+            BlendMode::Alpha => BlendState {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+            BlendMode::Additive => BlendState {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            },
+        }
+    }
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Opaque
+    }
+}
@@ -34,3 +34,14 @@ impl From<Color> for [f32; 4] {
         [color.r, color.g, color.b, color.a]
     }
 }
+
+impl From<Color> for wgpu::Color {
+    fn from(color: Color) -> Self {
+        wgpu::Color {
+            r: color.r as f64,
+            g: color.g as f64,
+            b: color.b as f64,
+            a: color.a as f64,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -17,3 +18,27 @@ pub enum ScreenCreateError {
         file_name: &'static str,
     },
 }
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("Failed to get the next swap chain frame")]
+    SwapChainFailure(#[source] wgpu::SwapChainError),
+
+    #[error("Failed to read back the rendered frame")]
+    ReadbackFailure(#[source] wgpu::BufferAsyncError),
+
+    #[error("Frames can only be read back from an offscreen device")]
+    NotOffscreen,
+}
+
+#[derive(Debug, Error)]
+pub enum ImageSaveError {
+    #[error("Failed to create {}", path.display())]
+    CreateFailure {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("Failed to encode png")]
+    EncodeFailure(#[source] png::EncodingError),
+}
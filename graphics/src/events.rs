@@ -1,5 +1,7 @@
 use nalgebra::Point2;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Key {
     Key1,
     Key2,
@@ -87,6 +89,53 @@ pub enum Key {
     Slash,
     Subtract,
     Tab,
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+}
+
+/// Gamepad buttons, named by position so they do not depend on the controller's labels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Analog gamepad inputs.
+/// Sticks go from -1.0 to 1.0, with positive x to the right and positive y up.
+/// Triggers go from 0.0 to 1.0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
 }
 
 /// Possible event types that can occur
@@ -111,4 +160,24 @@ pub enum Event {
 
     /// Middle mouse button.
     MouseMMB { down: bool },
+
+    /// A gamepad was plugged in. id identifies it in later gamepad events.
+    GamepadConnected { id: usize },
+
+    /// A gamepad was unplugged.
+    GamepadDisconnected { id: usize },
+
+    /// Gamepad button was pressed or released.
+    GamepadButton {
+        id: usize,
+        button: GamepadButton,
+        down: bool,
+    },
+
+    /// Gamepad stick or trigger moved.
+    GamepadAxis {
+        id: usize,
+        axis: GamepadAxis,
+        value: f32,
+    },
 }
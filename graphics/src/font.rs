@@ -0,0 +1,129 @@
+use crate::graphic_device::GraphicDevice;
+use crate::shape::Shape;
+use crate::utils;
+use nalgebra::Point2;
+use std::collections::HashMap;
+
+// Glyphs are drawn on a grid 4 units wide and 6 units tall,
+// with the origin at the bottom left.
+const GRID_HEIGHT: f32 = 6.0;
+const GRID_SPACING: f32 = 1.5;
+const GRID_ADVANCE: f32 = 4.0 + GRID_SPACING;
+
+// Thickness of the strokes, in grid units.
+const STROKE_THICKNESS: f32 = 0.6;
+
+type Stroke = &'static [(f32, f32)];
+
+#[rustfmt::skip]
+const GLYPHS: &[(char, &[Stroke])] = &[
+    ('A', &[&[(0.0, 0.0), (0.0, 4.0), (2.0, 6.0), (4.0, 4.0), (4.0, 0.0)], &[(0.0, 3.0), (4.0, 3.0)]]),
+    ('B', &[&[(0.0, 0.0), (0.0, 6.0), (3.0, 6.0), (4.0, 5.0), (4.0, 4.0), (3.0, 3.0), (0.0, 3.0)],
+            &[(3.0, 3.0), (4.0, 2.0), (4.0, 1.0), (3.0, 0.0), (0.0, 0.0)]]),
+    ('C', &[&[(4.0, 0.0), (0.0, 0.0), (0.0, 6.0), (4.0, 6.0)]]),
+    ('D', &[&[(0.0, 0.0), (0.0, 6.0), (2.0, 6.0), (4.0, 4.0), (4.0, 2.0), (2.0, 0.0), (0.0, 0.0)]]),
+    ('E', &[&[(4.0, 0.0), (0.0, 0.0), (0.0, 6.0), (4.0, 6.0)], &[(0.0, 3.0), (3.0, 3.0)]]),
+    ('F', &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 6.0)], &[(0.0, 3.0), (3.0, 3.0)]]),
+    ('G', &[&[(4.0, 5.0), (4.0, 6.0), (0.0, 6.0), (0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (2.0, 3.0)]]),
+    ('H', &[&[(0.0, 0.0), (0.0, 6.0)], &[(4.0, 0.0), (4.0, 6.0)], &[(0.0, 3.0), (4.0, 3.0)]]),
+    ('I', &[&[(0.0, 0.0), (4.0, 0.0)], &[(2.0, 0.0), (2.0, 6.0)], &[(0.0, 6.0), (4.0, 6.0)]]),
+    ('J', &[&[(0.0, 2.0), (0.0, 0.0), (4.0, 0.0), (4.0, 6.0)]]),
+    ('K', &[&[(0.0, 0.0), (0.0, 6.0)], &[(4.0, 6.0), (0.0, 3.0), (4.0, 0.0)]]),
+    ('L', &[&[(0.0, 6.0), (0.0, 0.0), (4.0, 0.0)]]),
+    ('M', &[&[(0.0, 0.0), (0.0, 6.0), (2.0, 4.0), (4.0, 6.0), (4.0, 0.0)]]),
+    ('N', &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 0.0), (4.0, 6.0)]]),
+    ('O', &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 6.0), (4.0, 0.0), (0.0, 0.0)]]),
+    ('P', &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 6.0), (4.0, 3.0), (0.0, 3.0)]]),
+    ('Q', &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 6.0), (4.0, 2.0), (2.0, 0.0), (0.0, 0.0)],
+            &[(2.0, 2.0), (4.0, 0.0)]]),
+    ('R', &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 6.0), (4.0, 3.0), (0.0, 3.0), (4.0, 0.0)]]),
+    ('S', &[&[(4.0, 6.0), (0.0, 6.0), (0.0, 3.0), (4.0, 3.0), (4.0, 0.0), (0.0, 0.0)]]),
+    ('T', &[&[(0.0, 6.0), (4.0, 6.0)], &[(2.0, 6.0), (2.0, 0.0)]]),
+    ('U', &[&[(0.0, 6.0), (0.0, 0.0), (4.0, 0.0), (4.0, 6.0)]]),
+    ('V', &[&[(0.0, 6.0), (2.0, 0.0), (4.0, 6.0)]]),
+    ('W', &[&[(0.0, 6.0), (0.0, 0.0), (2.0, 2.0), (4.0, 0.0), (4.0, 6.0)]]),
+    ('X', &[&[(0.0, 0.0), (4.0, 6.0)], &[(0.0, 6.0), (4.0, 0.0)]]),
+    ('Y', &[&[(0.0, 6.0), (2.0, 3.0), (4.0, 6.0)], &[(2.0, 3.0), (2.0, 0.0)]]),
+    ('Z', &[&[(0.0, 6.0), (4.0, 6.0), (0.0, 0.0), (4.0, 0.0)]]),
+    ('0', &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 6.0), (4.0, 0.0), (0.0, 0.0)], &[(0.0, 0.0), (4.0, 6.0)]]),
+    ('1', &[&[(1.0, 5.0), (2.0, 6.0), (2.0, 0.0)], &[(1.0, 0.0), (3.0, 0.0)]]),
+    ('2', &[&[(0.0, 6.0), (4.0, 6.0), (4.0, 3.0), (0.0, 3.0), (0.0, 0.0), (4.0, 0.0)]]),
+    ('3', &[&[(0.0, 6.0), (4.0, 6.0), (4.0, 0.0), (0.0, 0.0)], &[(0.0, 3.0), (4.0, 3.0)]]),
+    ('4', &[&[(0.0, 6.0), (0.0, 3.0), (4.0, 3.0)], &[(4.0, 6.0), (4.0, 0.0)]]),
+    ('5', &[&[(4.0, 6.0), (0.0, 6.0), (0.0, 3.0), (3.0, 3.0), (4.0, 2.0), (4.0, 1.0), (3.0, 0.0),
+              (0.0, 0.0)]]),
+    ('6', &[&[(4.0, 6.0), (0.0, 6.0), (0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (0.0, 3.0)]]),
+    ('7', &[&[(0.0, 6.0), (4.0, 6.0), (4.0, 0.0)]]),
+    ('8', &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 6.0), (4.0, 0.0), (0.0, 0.0)], &[(0.0, 3.0), (4.0, 3.0)]]),
+    ('9', &[&[(4.0, 3.0), (0.0, 3.0), (0.0, 6.0), (4.0, 6.0), (4.0, 0.0), (0.0, 0.0)]]),
+    ('.', &[&[(2.0, 0.0), (2.0, 0.5)]]),
+    (',', &[&[(2.0, 1.0), (1.0, -1.0)]]),
+    (':', &[&[(2.0, 1.0), (2.0, 1.5)], &[(2.0, 4.0), (2.0, 4.5)]]),
+    ('\'', &[&[(2.0, 6.0), (2.0, 4.0)]]),
+    ('-', &[&[(1.0, 3.0), (3.0, 3.0)]]),
+    ('+', &[&[(1.0, 3.0), (3.0, 3.0)], &[(2.0, 2.0), (2.0, 4.0)]]),
+    ('=', &[&[(1.0, 2.0), (3.0, 2.0)], &[(1.0, 4.0), (3.0, 4.0)]]),
+    ('_', &[&[(0.0, 0.0), (4.0, 0.0)]]),
+    ('/', &[&[(0.0, 0.0), (4.0, 6.0)]]),
+    ('!', &[&[(2.0, 2.0), (2.0, 6.0)], &[(2.0, 0.0), (2.0, 0.5)]]),
+    ('?', &[&[(0.0, 5.0), (1.0, 6.0), (3.0, 6.0), (4.0, 5.0), (4.0, 4.0), (2.0, 3.0), (2.0, 2.0)],
+            &[(2.0, 0.0), (2.0, 0.5)]]),
+    ('<', &[&[(3.0, 5.0), (1.0, 3.0), (3.0, 1.0)]]),
+    ('>', &[&[(1.0, 5.0), (3.0, 3.0), (1.0, 1.0)]]),
+    ('(', &[&[(3.0, 6.0), (2.0, 5.0), (2.0, 1.0), (3.0, 0.0)]]),
+    (')', &[&[(1.0, 6.0), (2.0, 5.0), (2.0, 1.0), (1.0, 0.0)]]),
+];
+
+/// A vector stroke font, with one shape per glyph.
+pub(crate) struct Font {
+    glyphs: HashMap<char, Shape>,
+}
+
+impl Font {
+    pub(crate) fn new(device: &mut GraphicDevice) -> Self {
+        let mut glyphs = HashMap::with_capacity(GLYPHS.len());
+
+        for (c, strokes) in GLYPHS {
+            let mut segments = Vec::new();
+            for stroke in strokes.iter() {
+                for pair in stroke.windows(2) {
+                    segments.push((grid_to_glyph(pair[0]), grid_to_glyph(pair[1])));
+                }
+            }
+
+            let (vertex_data, indices) =
+                utils::build_line_segments(&segments, STROKE_THICKNESS / GRID_HEIGHT);
+            let shape = device.create_shape(&vertex_data, &indices, "Glyph");
+
+            glyphs.insert(*c, shape);
+        }
+
+        Font { glyphs }
+    }
+
+    /// The shape for c, which has a height of 1.0.
+    /// Letters are always drawn in upper case.
+    pub(crate) fn glyph(&self, c: char) -> Option<&Shape> {
+        self.glyphs.get(&c.to_ascii_uppercase())
+    }
+}
+
+/// Horizontal distance from the start of one character to the next,
+/// for characters of the given size.
+pub fn char_advance(size: f32) -> f32 {
+    size * GRID_ADVANCE / GRID_HEIGHT
+}
+
+/// Width of text when drawn with characters of the given size.
+pub fn text_width(size: f32, text: &str) -> f32 {
+    let num_chars = text.chars().count();
+    if num_chars == 0 {
+        0.0
+    } else {
+        char_advance(size) * num_chars as f32 - size * GRID_SPACING / GRID_HEIGHT
+    }
+}
+
+fn grid_to_glyph((x, y): (f32, f32)) -> Point2<f32> {
+    Point2::new(x / GRID_HEIGHT, y / GRID_HEIGHT)
+}
@@ -1,27 +1,47 @@
+use crate::blend::BlendMode;
 use crate::color::Color;
-use crate::errors::ScreenCreateError;
+use crate::errors::{RenderError, ScreenCreateError};
+use crate::image::{self, Image};
 use crate::model_transform::ModelTransform;
-use crate::shape::{Shape, ShapeData};
+use crate::shape::{Instances, Shape, ShapeData};
 use crate::uniforms::ViewUniforms;
+use crate::utils;
 use crate::vertex::Vertex;
-use nalgebra::Matrix4;
+use nalgebra::{Matrix4, Point2};
 use std::sync::{Arc, Mutex, MutexGuard, Weak};
 use std::vec::Vec;
 use wgpu::{util::DeviceExt, IndexFormat};
 use wgpu::{
     BindGroup, Buffer, BufferAddress, BufferUsage, Device, Queue, RenderPipeline, Surface,
-    SwapChain, SwapChainDescriptor,
+    SwapChain, SwapChainDescriptor, Texture, TextureFormat, TextureView,
 };
 use winit::{dpi::PhysicalSize, window::Window};
 use zerocopy::AsBytes;
 
+// Window and offscreen targets share a format, so they can share a pipeline.
+const TARGET_FORMAT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
+
+// Outline corners reach at most this many times half the line thickness from the point.
+const MITER_LIMIT: f32 = 4.0;
+
+enum RenderTarget {
+    Window {
+        surface: Surface,
+        sc_desc: SwapChainDescriptor,
+        swap_chain: SwapChain,
+    },
+    Offscreen {
+        texture: Texture,
+        size: PhysicalSize<u32>,
+    },
+}
+
 pub struct GraphicDevice {
-    surface: Surface,
+    target: RenderTarget,
     device: Device,
     queue: Queue,
-    sc_desc: SwapChainDescriptor,
-    swap_chain: SwapChain,
-    render_pipeline: RenderPipeline,
+    // Indexed by BlendMode::index.
+    render_pipelines: Vec<RenderPipeline>,
 
     view_uniform_buffer: Buffer,
     view_uniform_bind_group: BindGroup,
@@ -37,30 +57,12 @@ impl GraphicDevice {
 
         let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
         let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .ok_or(ScreenCreateError::AdapterCreateFailure)?;
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("Main Device"),
-                    features: wgpu::Features::empty(),
-                    limits: Default::default(),
-                },
-                None,
-            )
-            .await
-            .map_err(ScreenCreateError::DeviceCreateFailure)?;
+        let (device, queue) = request_device(&instance, Some(&surface)).await?;
 
         let sc_desc = SwapChainDescriptor {
             usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
             // We should query for format.
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            format: TARGET_FORMAT,
             width: physical_size.width,
             height: physical_size.height,
             present_mode: wgpu::PresentMode::Mailbox,
@@ -69,6 +71,39 @@ impl GraphicDevice {
 
         let dpi_factor = window.scale_factor();
 
+        let target = RenderTarget::Window {
+            surface,
+            sc_desc,
+            swap_chain,
+        };
+        let device = GraphicDevice::new(device, queue, target, physical_size);
+
+        Ok((device, physical_size, dpi_factor))
+    }
+
+    /// Creates a device that renders into a texture instead of a window.
+    /// Any adapter will do, including software ones such as lavapipe.
+    pub async fn create_headless(
+        width: u32,
+        height: u32,
+    ) -> Result<GraphicDevice, ScreenCreateError> {
+        let size = PhysicalSize::new(width, height);
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let (device, queue) = request_device(&instance, None).await?;
+
+        let texture = create_target_texture(&device, size);
+        let target = RenderTarget::Offscreen { texture, size };
+
+        Ok(GraphicDevice::new(device, queue, target, size))
+    }
+
+    fn new(
+        device: Device,
+        queue: Queue,
+        target: RenderTarget,
+        physical_size: PhysicalSize<u32>,
+    ) -> Self {
         let view_uniforms = ViewUniforms::from(physical_size);
         let view_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("view_uniforms"),
@@ -107,77 +142,80 @@ impl GraphicDevice {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = {
+        let render_pipelines = {
             let vs_spirv = wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/simple.vert.spv"));
             let fs_spirv = wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/simple.frag.spv"));
-            // let vs_data = wgpu::read_spirv(std::io::Cursor::new(vs_spirv)).map_err(|err| {
-            //     ScreenCreateError::PipelineFailure {
-            //         source: err,
-            //         file_name: "simple.vert",
-            //     }
-            // })?;
-            // let fs_data = wgpu::read_spirv(std::io::Cursor::new(fs_spirv)).map_err(|err| {
-            //     ScreenCreateError::PipelineFailure {
-            //         source: err,
-            //         file_name: "simple.frag",
-            //     }
-            // })?;
             let vs_module = device.create_shader_module(&vs_spirv);
             let fs_module = device.create_shader_module(&fs_spirv);
 
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("MainRenderPipeline"),
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &vs_module,
-                    entry_point: "main",
-                    buffers: &[Vertex::desc(), Color::desc(), ModelTransform::desc()],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &fs_module,
-                    entry_point: "main",
-                    targets: &[sc_desc.format.into()],
-                    // targets: &[wgpu::ColorTargetState {
-                    //     format: sc_desc.format,
-                    //     color_blend: wgpu::BlendState::REPLACE,
-                    //     alpha_blend: wgpu::BlendState::REPLACE,
-                    //     write_mask: wgpu::ColorWrite::ALL,
-                    // }],
-                }),
-
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: wgpu::CullMode::Back,
-                    ..Default::default()
-                },
-
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
-            })
+            BlendMode::ALL
+                .iter()
+                .map(|&blend| {
+                    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("MainRenderPipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                            module: &vs_module,
+                            entry_point: "main",
+                            buffers: &[Vertex::desc(), Color::desc(), ModelTransform::desc()],
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &fs_module,
+                            entry_point: "main",
+                            targets: &[wgpu::ColorTargetState {
+                                format: TARGET_FORMAT,
+                                color_blend: blend.color_blend(),
+                                alpha_blend: blend.alpha_blend(),
+                                write_mask: wgpu::ColorWrite::ALL,
+                            }],
+                        }),
+
+                        primitive: wgpu::PrimitiveState {
+                            topology: wgpu::PrimitiveTopology::TriangleList,
+                            front_face: wgpu::FrontFace::Ccw,
+                            cull_mode: wgpu::CullMode::Back,
+                            ..Default::default()
+                        },
+
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                    })
+                })
+                .collect()
         };
 
-        let device = GraphicDevice {
-            surface,
+        GraphicDevice {
+            target,
             device,
             queue,
-            sc_desc,
-            swap_chain,
-            render_pipeline,
+            render_pipelines,
 
             view_uniform_buffer,
             view_uniform_bind_group,
 
             shapes: Vec::new(),
-        };
-
-        Ok((device, physical_size, dpi_factor))
+        }
     }
 
     pub fn set_window_size(&mut self, new_size: PhysicalSize<u32>) {
-        self.sc_desc.width = new_size.width;
-        self.sc_desc.height = new_size.height;
-        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        match self.target {
+            RenderTarget::Window {
+                ref surface,
+                ref mut sc_desc,
+                ref mut swap_chain,
+            } => {
+                sc_desc.width = new_size.width;
+                sc_desc.height = new_size.height;
+                *swap_chain = self.device.create_swap_chain(surface, sc_desc);
+            }
+            RenderTarget::Offscreen {
+                ref mut texture,
+                ref mut size,
+            } => {
+                *texture = create_target_texture(&self.device, new_size);
+                *size = new_size;
+            }
+        }
 
         let view_uniforms = ViewUniforms::from(new_size);
 
@@ -223,22 +261,108 @@ impl GraphicDevice {
         Shape { data, name }
     }
 
-    pub fn draw_shape(&mut self, transform: Matrix4<f32>, color: Color, shape: &Shape) {
+    pub fn create_shape_from_points(
+        &mut self,
+        points: &[Point2<f32>],
+        indices: &[u16],
+        name: &'static str,
+    ) -> Shape {
+        let vertex_data: Vec<Vertex> = points.iter().map(|p| Vertex::new(p.x, p.y)).collect();
+
+        self.create_shape(vertex_data.as_slice(), indices, name)
+    }
+
+    pub fn create_circle(&mut self, radius: f32, vertices: usize, name: &'static str) -> Shape {
+        let (vertex_data, indices) = utils::build_circle(radius, vertices);
+
+        self.create_shape(vertex_data.as_slice(), &indices, name)
+    }
+
+    pub fn create_outline(
+        &mut self,
+        points: &[Point2<f32>],
+        thickness: f32,
+        name: &'static str,
+    ) -> Shape {
+        let (vertex_data, indices) = utils::build_outline(points, thickness, MITER_LIMIT);
+
+        self.create_shape(vertex_data.as_slice(), &indices, name)
+    }
+
+    pub fn draw_shape(
+        &mut self,
+        transform: Matrix4<f32>,
+        color: Color,
+        blend: BlendMode,
+        shape: &Shape,
+    ) {
         let mut shape_data = shape.data.lock().unwrap();
 
         // Add this draw request to our instances.
-        shape_data
-            .instance_transforms
-            .push(ModelTransform::new(transform));
-        shape_data.instance_colors.push(color);
+        let instances = &mut shape_data.instances[blend.index()];
+        instances.transforms.push(ModelTransform::new(transform));
+        instances.colors.push(color);
     }
 
     pub fn render_frame(&mut self, clear_color: wgpu::Color) -> Result<(), wgpu::SwapChainError> {
         self.shapes
             .retain(|shape_data| shape_data.strong_count() > 0);
 
-        let frame = self.swap_chain.get_current_frame()?;
+        match self.target {
+            RenderTarget::Window { ref swap_chain, .. } => {
+                let frame = swap_chain.get_current_frame()?;
+                self.draw_frame(&[&frame.output.view], clear_color);
+            }
+            RenderTarget::Offscreen { ref texture, .. } => {
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                self.draw_frame(&[&view], clear_color);
+            }
+        }
+
+        Ok(())
+    }
 
+    /// Reads back the last frame rendered by an offscreen device.
+    pub fn read_frame(&self) -> Result<Image, RenderError> {
+        match self.target {
+            RenderTarget::Offscreen { ref texture, size } => self.read_texture(texture, size),
+            RenderTarget::Window { .. } => Err(RenderError::NotOffscreen),
+        }
+    }
+
+    /// Renders a frame, like render_frame, and also reads it back.
+    pub fn capture_frame(&mut self, clear_color: wgpu::Color) -> Result<Image, RenderError> {
+        let (swap_chain, sc_desc) = match self.target {
+            RenderTarget::Window {
+                ref swap_chain,
+                ref sc_desc,
+                ..
+            } => (swap_chain, sc_desc),
+            RenderTarget::Offscreen { .. } => {
+                self.render_frame(clear_color)
+                    .map_err(RenderError::SwapChainFailure)?;
+                return self.read_frame();
+            }
+        };
+
+        self.shapes
+            .retain(|shape_data| shape_data.strong_count() > 0);
+
+        // Swap chain frames cannot be copied from, so draw the
+        // frame a second time into a texture that can be.
+        let size = PhysicalSize::new(sc_desc.width, sc_desc.height);
+        let capture_texture = create_target_texture(&self.device, size);
+        let capture_view = capture_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let frame = swap_chain
+            .get_current_frame()
+            .map_err(RenderError::SwapChainFailure)?;
+        self.draw_frame(&[&frame.output.view, &capture_view], clear_color);
+
+        self.read_texture(&capture_texture, size)
+    }
+
+    fn draw_frame(&self, views: &[&TextureView], clear_color: wgpu::Color) {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -267,43 +391,151 @@ impl GraphicDevice {
                 }
             }
 
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.output.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(clear_color),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
+            // Every view gets the same frame.
+            for &view in views {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                        attachment: view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(clear_color),
+                            store: true,
+                        },
+                    }],
+                    depth_stencil_attachment: None,
+                });
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.view_uniform_bind_group, &[]);
-
-            for shape_render_pass in &shape_render_pass_data {
-                render_pass.set_vertex_buffer(0, shape_render_pass.vertex_buffer.slice(..));
-                render_pass
-                    .set_vertex_buffer(1, shape_render_pass.instance_colors_buffer.slice(..));
-                render_pass
-                    .set_vertex_buffer(2, shape_render_pass.instance_transforms_buffer.slice(..));
-                render_pass.set_index_buffer(
-                    shape_render_pass.index_buffer.slice(..),
-                    IndexFormat::Uint16,
-                );
-
-                render_pass.draw_indexed(
-                    0..shape_render_pass.num_indices,
-                    0,
-                    0..shape_render_pass.num_instances,
-                );
+                render_pass.set_bind_group(0, &self.view_uniform_bind_group, &[]);
+
+                // Each blend mode is a separate pass over the shapes, in the order of BlendMode::ALL.
+                for (pipeline, blend_index) in self.render_pipelines.iter().zip(0..) {
+                    render_pass.set_pipeline(pipeline);
+
+                    for shape_render_pass in &shape_render_pass_data {
+                        let instance_buffers = match shape_render_pass.instances[blend_index] {
+                            Some(ref instance_buffers) => instance_buffers,
+                            None => continue,
+                        };
+
+                        render_pass.set_vertex_buffer(0, shape_render_pass.vertex_buffer.slice(..));
+                        render_pass.set_vertex_buffer(1, instance_buffers.colors_buffer.slice(..));
+                        render_pass
+                            .set_vertex_buffer(2, instance_buffers.transforms_buffer.slice(..));
+                        render_pass.set_index_buffer(
+                            shape_render_pass.index_buffer.slice(..),
+                            IndexFormat::Uint16,
+                        );
+
+                        render_pass.draw_indexed(
+                            0..shape_render_pass.num_indices,
+                            0,
+                            0..instance_buffers.num_instances,
+                        );
+                    }
+                }
             }
         }
         self.queue.submit(Some(encoder.finish()));
-        Ok(())
     }
+
+    fn read_texture(
+        &self,
+        texture: &Texture,
+        size: PhysicalSize<u32>,
+    ) -> Result<Image, RenderError> {
+        let padded_bytes_per_row = image::padded_bytes_per_row(size.width);
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: (padded_bytes_per_row * size.height) as BufferAddress,
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("ReadTexture"),
+            });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: padded_bytes_per_row,
+                    rows_per_image: size.height,
+                },
+            },
+            wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth: 1,
+            },
+        );
+
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        self.device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(mapping).map_err(RenderError::ReadbackFailure)?;
+
+        let image = {
+            let data = slice.get_mapped_range();
+            Image::from_padded_bgra(size.width, size.height, &data)
+        };
+        buffer.unmap();
+
+        Ok(image)
+    }
+}
+
+async fn request_device(
+    instance: &wgpu::Instance,
+    compatible_surface: Option<&Surface>,
+) -> Result<(Device, Queue), ScreenCreateError> {
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface,
+        })
+        .await
+        .ok_or(ScreenCreateError::AdapterCreateFailure)?;
+
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Main Device"),
+                features: wgpu::Features::empty(),
+                limits: Default::default(),
+            },
+            None,
+        )
+        .await
+        .map_err(ScreenCreateError::DeviceCreateFailure)
+}
+
+fn create_target_texture(device: &Device, size: PhysicalSize<u32>) -> Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("offscreen_target"),
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TARGET_FORMAT,
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+    })
 }
 
 struct ShapeRenderPassData<'a> {
@@ -311,48 +543,65 @@ struct ShapeRenderPassData<'a> {
     index_buffer: &'a Buffer,
     num_indices: u32,
 
-    instance_transforms_buffer: Buffer,
-    instance_colors_buffer: Buffer,
+    // Indexed by BlendMode::index, None when there is nothing to draw in that mode.
+    instances: Vec<Option<InstanceBuffers>>,
+}
+
+struct InstanceBuffers {
+    transforms_buffer: Buffer,
+    colors_buffer: Buffer,
     num_instances: u32,
 }
 
 impl<'a> ShapeRenderPassData<'a> {
     fn create(shape_data: &'a mut ShapeData, device: &Device) -> Option<Self> {
-        if !shape_data.instance_transforms.is_empty() {
-            let vertex_buffer = &shape_data.vertex_buffer;
-            let index_buffer = &shape_data.index_buffer;
-            let num_indices = shape_data.num_indices;
-
-            let instance_transforms_buffer =
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("instance_transforms"),
-                    contents: shape_data.instance_transforms.as_bytes(),
-                    usage: BufferUsage::VERTEX,
-                });
+        let instances: Vec<Option<InstanceBuffers>> = shape_data
+            .instances
+            .iter_mut()
+            .map(|instances| InstanceBuffers::create(instances, device))
+            .collect();
+
+        if instances.iter().all(Option::is_none) {
+            return None;
+        }
 
-            let instance_colors_buffer =
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("instance_colors"),
-                    contents: shape_data.instance_colors.as_bytes(),
-                    usage: BufferUsage::VERTEX,
-                });
+        Some(Self {
+            vertex_buffer: &shape_data.vertex_buffer,
+            index_buffer: &shape_data.index_buffer,
+            num_indices: shape_data.num_indices,
 
-            let num_instances = shape_data.instance_transforms.len() as u32;
+            instances,
+        })
+    }
+}
 
-            shape_data.instance_transforms.clear();
-            shape_data.instance_colors.clear();
+impl InstanceBuffers {
+    fn create(instances: &mut Instances, device: &Device) -> Option<Self> {
+        if instances.transforms.is_empty() {
+            return None;
+        }
 
-            Some(Self {
-                vertex_buffer,
-                index_buffer,
-                num_indices,
+        let transforms_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("instance_transforms"),
+            contents: instances.transforms.as_bytes(),
+            usage: BufferUsage::VERTEX,
+        });
 
-                instance_transforms_buffer,
-                instance_colors_buffer,
-                num_instances,
-            })
-        } else {
-            None
-        }
+        let colors_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("instance_colors"),
+            contents: instances.colors.as_bytes(),
+            usage: BufferUsage::VERTEX,
+        });
+
+        let num_instances = instances.transforms.len() as u32;
+
+        instances.transforms.clear();
+        instances.colors.clear();
+
+        Some(Self {
+            transforms_buffer,
+            colors_buffer,
+            num_instances,
+        })
     }
 }
@@ -0,0 +1,124 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::errors::ImageSaveError;
+
+/// An RGBA image with 8 bits per channel, stored row by row from the top left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), (4 * width * height) as usize);
+
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Converts BGRA rows, as read back from a texture, where each row is
+    /// padded out to padded_bytes_per_row(width).
+    pub(crate) fn from_padded_bgra(width: u32, height: u32, data: &[u8]) -> Self {
+        let unpadded_bytes_per_row = (4 * width) as usize;
+        let padded_bytes_per_row = padded_bytes_per_row(width) as usize;
+
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * height as usize);
+        for row in data.chunks(padded_bytes_per_row).take(height as usize) {
+            for bgra in row[..unpadded_bytes_per_row].chunks(4) {
+                pixels.extend_from_slice(&[bgra[2], bgra[1], bgra[0], bgra[3]]);
+            }
+        }
+
+        Image::new(width, height, pixels)
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let start = (4 * (y * self.width + x)) as usize;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.pixels[start..start + 4]);
+        pixel
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageSaveError> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|err| ImageSaveError::CreateFailure {
+            source: err,
+            path: path.to_path_buf(),
+        })?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder
+            .write_header()
+            .map_err(ImageSaveError::EncodeFailure)?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(ImageSaveError::EncodeFailure)
+    }
+}
+
+/// Texture copies need aligned buffer rows, so there may be padding after
+/// each row of pixels.
+pub(crate) fn padded_bytes_per_row(width: u32) -> u32 {
+    let unpadded_bytes_per_row = 4 * width;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (unpadded_bytes_per_row + align - 1) / align * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{padded_bytes_per_row, Image};
+
+    #[test]
+    fn rows_are_padded_to_alignment() {
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        assert_eq!(padded_bytes_per_row(1), align);
+        assert_eq!(padded_bytes_per_row(align / 4), align);
+        assert_eq!(padded_bytes_per_row(align / 4 + 1), 2 * align);
+    }
+
+    #[test]
+    fn strips_padding_and_swaps_to_rgba() {
+        let (width, height) = (3, 2);
+        let padded = padded_bytes_per_row(width) as usize;
+
+        // Each pixel is BGRA, with its row and column in blue and green,
+        // and the padding filled with junk.
+        let mut data = vec![0xee; padded * height as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let at = y as usize * padded + 4 * x as usize;
+                data[at..at + 4].copy_from_slice(&[y as u8, x as u8, 200, 255]);
+            }
+        }
+
+        let image = Image::from_padded_bgra(width, height, &data);
+        assert_eq!(image.pixels().len(), (4 * width * height) as usize);
+        assert_eq!(image.pixel(0, 0), [200, 0, 0, 255]);
+        assert_eq!(image.pixel(2, 0), [200, 2, 0, 255]);
+        assert_eq!(image.pixel(1, 1), [200, 1, 1, 255]);
+        assert!(!image.pixels().contains(&0xee));
+    }
+}
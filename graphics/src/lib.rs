@@ -1,7 +1,11 @@
+pub mod blend;
 pub mod color;
 pub mod errors;
 pub mod events;
+pub mod font;
+pub mod image;
 pub mod model;
+pub mod offscreen;
 pub mod screen;
 pub mod shape;
 
@@ -1,3 +1,4 @@
+use crate::blend::BlendMode;
 use crate::color::Color;
 use crate::shape::Shape;
 
@@ -8,6 +9,7 @@ pub struct Model {
     pub shape: Shape,
     pub color: Color,
     pub transform: Similarity2<f32>,
+    pub blend: BlendMode,
 }
 
 impl Model {
@@ -16,6 +18,12 @@ impl Model {
             shape,
             color,
             transform,
+            blend: BlendMode::Opaque,
         }
     }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
 }
@@ -0,0 +1,183 @@
+use nalgebra::Point2;
+
+use crate::color;
+use crate::errors::{RenderError, ScreenCreateError};
+use crate::font::Font;
+use crate::graphic_device::GraphicDevice;
+use crate::image::Image;
+use crate::screen::ScreenRender;
+use crate::shape::Shape;
+
+/// A screen without a window, that renders into an image.
+/// Useful for tests, since it does not need a display or a GPU.
+pub struct OffscreenScreen {
+    clear_color: wgpu::Color,
+
+    device: GraphicDevice,
+    font: Font,
+}
+
+impl OffscreenScreen {
+    pub fn create(
+        width: u32,
+        height: u32,
+        clear_color: color::Color,
+    ) -> Result<OffscreenScreen, ScreenCreateError> {
+        let mut device =
+            futures::executor::block_on(GraphicDevice::create_headless(width, height))?;
+        let font = Font::new(&mut device);
+
+        Ok(OffscreenScreen {
+            clear_color: clear_color.into(),
+            device,
+            font,
+        })
+    }
+
+    pub fn create_shape(
+        &mut self,
+        points: &[Point2<f32>],
+        indices: &[u16],
+        name: &'static str,
+    ) -> Shape {
+        self.device.create_shape_from_points(points, indices, name)
+    }
+
+    pub fn create_circle(&mut self, radius: f32, vertices: usize, name: &'static str) -> Shape {
+        self.device.create_circle(radius, vertices, name)
+    }
+
+    /// Creates a closed outline through points, in order, with lines thickness wide.
+    /// Drawn the same way as any other shape.
+    pub fn create_outline_shape(
+        &mut self,
+        points: &[Point2<f32>],
+        thickness: f32,
+        name: &'static str,
+    ) -> Shape {
+        self.device.create_outline(points, thickness, name)
+    }
+
+    /// Renders a frame with render, and returns the result.
+    pub fn render_frame<F: FnOnce(ScreenRender)>(
+        &mut self,
+        render: F,
+    ) -> Result<Image, RenderError> {
+        render(ScreenRender {
+            device: &mut self.device,
+            font: &self.font,
+        });
+
+        self.device
+            .render_frame(self.clear_color)
+            .map_err(RenderError::SwapChainFailure)?;
+        self.device.read_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OffscreenScreen;
+    use crate::blend::BlendMode;
+    use crate::color::Color;
+    use crate::shape::Shape;
+    use nalgebra::{Point2, Similarity2, Vector2};
+
+    /// A black 64x64 screen and a unit square.
+    /// These tests need an adapter, so they are ignored by default. The parts of
+    /// readback that run on the CPU are tested in image.rs.
+    ///
+    /// A software adapter is enough, so CI machines without a GPU can run them
+    /// with Mesa's lavapipe Vulkan driver installed (mesa-vulkan-drivers on
+    /// Debian and Ubuntu), using `cargo test -p graphics -- --ignored`.
+    fn screen_with_square() -> (OffscreenScreen, Shape) {
+        let black = Color::new(0.0, 0.0, 0.0, 1.0);
+        let mut screen =
+            OffscreenScreen::create(64, 64, black).expect("no adapter for offscreen rendering");
+
+        let square = screen.create_shape(
+            &[
+                Point2::new(-0.5, -0.5),
+                Point2::new(0.5, -0.5),
+                Point2::new(0.5, 0.5),
+                Point2::new(-0.5, 0.5),
+            ],
+            &[0, 1, 2, 0, 2, 3],
+            "square",
+        );
+
+        (screen, square)
+    }
+
+    #[test]
+    #[ignore = "needs a graphics adapter"]
+    fn renders_square() {
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        let (mut screen, square) = screen_with_square();
+
+        // Covers the top right quarter of the screen.
+        let transform = Similarity2::new(Vector2::new(0.5, 0.5), 0.0, 1.0);
+        let image = screen
+            .render_frame(|mut screen_render| {
+                screen_render.draw_shape(&transform, red, BlendMode::Opaque, &square)
+            })
+            .unwrap();
+
+        assert_eq!(image.width(), 64);
+        assert_eq!(image.height(), 64);
+        assert_eq!(image.pixel(48, 16), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(16, 16), [0, 0, 0, 255]);
+        assert_eq!(image.pixel(48, 48), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    #[ignore = "needs a graphics adapter"]
+    fn translucent_draws_blend_over_opaque() {
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        let translucent_blue = Color::new(0.0, 0.0, 1.0, 0.5);
+        let (mut screen, square) = screen_with_square();
+
+        let transform = Similarity2::new(Vector2::new(0.0, 0.0), 0.0, 1.0);
+        let image = screen
+            .render_frame(|mut screen_render| {
+                // Drawn before the opaque square, but must still end up on top of it.
+                screen_render.draw_shape(&transform, translucent_blue, BlendMode::Alpha, &square);
+                screen_render.draw_shape(&transform, red, BlendMode::Opaque, &square);
+            })
+            .unwrap();
+
+        let [r, g, b, _] = image.pixel(32, 32);
+        assert!(r > 0 && r < 255, "red was {}", r);
+        assert_eq!(g, 0);
+        assert!(b > 0 && b < 255, "blue was {}", b);
+    }
+
+    #[test]
+    #[ignore = "needs a graphics adapter"]
+    fn renders_outline() {
+        let white = Color::new(1.0, 1.0, 1.0, 1.0);
+        let (mut screen, _) = screen_with_square();
+
+        let outline = screen.create_outline_shape(
+            &[
+                Point2::new(-0.5, -0.5),
+                Point2::new(0.5, -0.5),
+                Point2::new(0.5, 0.5),
+                Point2::new(-0.5, 0.5),
+            ],
+            0.1,
+            "outline",
+        );
+
+        let transform = Similarity2::new(Vector2::new(0.0, 0.0), 0.0, 1.0);
+        let image = screen
+            .render_frame(|mut screen_render| {
+                screen_render.draw_shape(&transform, white, BlendMode::Opaque, &outline)
+            })
+            .unwrap();
+
+        // The edge is drawn, but the middle is left empty.
+        assert_eq!(image.pixel(48, 32), [255, 255, 255, 255]);
+        assert_eq!(image.pixel(32, 32), [0, 0, 0, 255]);
+    }
+}
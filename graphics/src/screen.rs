@@ -1,4 +1,5 @@
-use nalgebra::{Matrix4, Point2, Similarity2};
+use gilrs::Gilrs;
+use nalgebra::{Matrix4, Point2, Similarity2, Vector2};
 use std::time::{Duration, Instant};
 use winit::{
     dpi::{LogicalSize, PhysicalSize},
@@ -7,20 +8,33 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+use crate::blend::BlendMode;
 use crate::color;
 use crate::cursor::Cursor;
-use crate::errors::ScreenCreateError;
+use crate::errors::{RenderError, ScreenCreateError};
 use crate::events;
+use crate::font::{self, Font};
 use crate::graphic_device::GraphicDevice;
+use crate::image::Image;
 use crate::model;
 use crate::shape::Shape;
-use crate::utils;
-use crate::vertex::Vertex;
 
 pub trait ScreenCallbacks {
-    fn handle_event(&mut self, _screen: &mut Screen, _event: events::Event) {}
+    /// By default, pressing escape exits.
+    fn handle_event(&mut self, screen: &mut Screen, event: events::Event) {
+        if let events::Event::KeyPress {
+            key: events::Key::Escape,
+            down: true,
+        } = event
+        {
+            screen.exit();
+        }
+    }
     fn update(&mut self, _screen: &mut Screen, _frame_delta: Duration) {}
     fn render(&self, _screen_render: ScreenRender) {}
+    /// Called with the frame requested by Screen::capture_frame, or the
+    /// error that stopped it from being captured.
+    fn frame_captured(&mut self, _screen: &mut Screen, _image: Result<Image, RenderError>) {}
 }
 
 pub struct ScreenRunner {
@@ -54,13 +68,19 @@ pub struct Screen {
     clear_color: wgpu::Color,
 
     device: GraphicDevice,
+    font: Font,
     cursor: Cursor,
+    // None if gamepads are not supported on this platform.
+    gamepads: Option<Gilrs>,
 
     physical_size: PhysicalSize<u32>,
     dpi_factor: f64,
 
     last_frame_time: Instant,
     next_frame_time: Instant,
+
+    exit_requested: bool,
+    capture_requested: bool,
 }
 
 impl Screen {
@@ -77,26 +97,25 @@ impl Screen {
             .build(event_loop)
             .map_err(ScreenCreateError::WindowCreateFailure)?;
 
-        let (device, physical_size, dpi_factor) =
+        let (mut device, physical_size, dpi_factor) =
             futures::executor::block_on(GraphicDevice::create(&window))?;
-
-        let wgpu_clear_color = wgpu::Color {
-            r: clear_color.r as f64,
-            g: clear_color.g as f64,
-            b: clear_color.b as f64,
-            a: clear_color.a as f64,
-        };
+        let font = Font::new(&mut device);
 
         Ok(Screen {
             window,
-            clear_color: wgpu_clear_color,
+            clear_color: clear_color.into(),
             device,
+            font,
             cursor: Cursor::new(physical_size),
+            gamepads: Gilrs::new().ok(),
             physical_size,
             dpi_factor,
 
             last_frame_time: Instant::now(),
             next_frame_time: Instant::now(),
+
+            exit_requested: false,
+            capture_requested: false,
         })
     }
 
@@ -106,23 +125,22 @@ impl Screen {
         indices: &[u16],
         name: &'static str,
     ) -> Shape {
-        let size_points = points.len();
-
-        let mut vertex_data = Vec::with_capacity(size_points);
-
-        for p in points.iter() {
-            vertex_data.push(Vertex::new(p.x, p.y));
-        }
-
-        self.device
-            .create_shape(vertex_data.as_slice(), &indices, name)
+        self.device.create_shape_from_points(points, indices, name)
     }
 
     pub fn create_circle(&mut self, radius: f32, vertices: usize, name: &'static str) -> Shape {
-        let (vertex_data, indices) = utils::build_circle(radius, vertices);
+        self.device.create_circle(radius, vertices, name)
+    }
 
-        self.device
-            .create_shape(vertex_data.as_slice(), &indices, name)
+    /// Creates a closed outline through points, in order, with lines thickness wide.
+    /// Drawn the same way as any other shape.
+    pub fn create_outline_shape(
+        &mut self,
+        points: &[Point2<f32>],
+        thickness: f32,
+        name: &'static str,
+    ) -> Shape {
+        self.device.create_outline(points, thickness, name)
     }
 
     pub fn handle_event<C: 'static + ScreenCallbacks, T>(
@@ -146,6 +164,7 @@ impl Screen {
                 self.last_frame_time = Instant::now();
                 self.next_frame_time = self.last_frame_time + Duration::from_millis(16);
 
+                self.poll_gamepads(callbacks);
                 callbacks.update(self, frame_delta);
 
                 self.window.request_redraw();
@@ -157,15 +176,36 @@ impl Screen {
                 if window_id == self.window.id() {
                     callbacks.render(ScreenRender {
                         device: &mut self.device,
+                        font: &self.font,
                     });
 
-                    self.device
-                        .render_frame(self.clear_color)
-                        .expect("failed to render");
+                    if self.capture_requested {
+                        self.capture_requested = false;
+                        let image = self.device.capture_frame(self.clear_color);
+                        callbacks.frame_captured(self, image);
+                    } else {
+                        self.device
+                            .render_frame(self.clear_color)
+                            .expect("failed to render");
+                    }
                 }
             }
             _ => *control_flow = ControlFlow::WaitUntil(self.next_frame_time),
         }
+
+        if self.exit_requested {
+            *control_flow = ControlFlow::Exit;
+        }
+    }
+
+    /// Closes the screen once the current event has been handled.
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    /// Captures the next rendered frame, and passes it to ScreenCallbacks::frame_captured.
+    pub fn capture_frame(&mut self) {
+        self.capture_requested = true;
     }
 
     pub fn get_mouse_pos(&self) -> Point2<f32> {
@@ -178,16 +218,7 @@ impl Screen {
         callbacks: &mut C,
     ) -> ControlFlow {
         match window_event {
-            WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        state: ElementState::Pressed,
-                        virtual_keycode: Some(VirtualKeyCode::Escape),
-                        ..
-                    },
-                ..
-            }
-            | WindowEvent::CloseRequested => ControlFlow::Exit,
+            WindowEvent::CloseRequested => ControlFlow::Exit,
 
             WindowEvent::Resized(physical_size) => {
                 self.physical_size = *physical_size;
@@ -266,6 +297,21 @@ impl Screen {
         }
     }
 
+    fn poll_gamepads<C: ScreenCallbacks>(&mut self, callbacks: &mut C) {
+        let mut gamepad_events = Vec::new();
+        if let Some(ref mut gamepads) = self.gamepads {
+            while let Some(gilrs::Event { id, event, .. }) = gamepads.next_event() {
+                if let Some(event) = match_gamepad_event(id.into(), event) {
+                    gamepad_events.push(event);
+                }
+            }
+        }
+
+        for event in gamepad_events {
+            callbacks.handle_event(self, event);
+        }
+    }
+
     fn handle_resize<C: ScreenCallbacks>(&mut self, callbacks: &mut C) {
         self.device.set_window_size(self.physical_size);
         self.cursor.set_window_size(self.physical_size);
@@ -276,17 +322,25 @@ impl Screen {
 }
 
 pub struct ScreenRender<'a> {
-    device: &'a mut GraphicDevice,
+    pub(crate) device: &'a mut GraphicDevice,
+    pub(crate) font: &'a Font,
 }
 
 impl<'a> ScreenRender<'a> {
     /// Draws a model.
     pub fn draw_model(&mut self, model: &model::Model) {
-        self.draw_shape(&model.transform, model.color, &model.shape);
+        self.draw_shape(&model.transform, model.color, model.blend, &model.shape);
     }
 
-    // Draw a flat colored shape.
-    pub fn draw_shape(&mut self, transform: &Similarity2<f32>, color: color::Color, shape: &Shape) {
+    /// Draws a flat colored shape.
+    /// Opaque shapes are drawn first, then alpha blended ones, then additive ones.
+    pub fn draw_shape(
+        &mut self,
+        transform: &Similarity2<f32>,
+        color: color::Color,
+        blend: BlendMode,
+        shape: &Shape,
+    ) {
         let vals = transform.to_homogeneous();
         #[rustfmt::skip]
         let draw_transform = Matrix4::new(
@@ -295,7 +349,26 @@ impl<'a> ScreenRender<'a> {
             0.0, 0.0, 0.0, 0.0,
             vals[2], vals[5], 0.0, vals[8]);
 
-        self.device.draw_shape(draw_transform, color, shape);
+        self.device.draw_shape(draw_transform, color, blend, shape);
+    }
+
+    /// Draws text with the built in stroke font.
+    /// pos is the bottom left corner of the first character, and size is the character height.
+    /// Characters without a glyph are drawn as spaces.
+    /// Text is alpha blended, so it is drawn over opaque shapes.
+    pub fn draw_text(&mut self, pos: Point2<f32>, size: f32, color: color::Color, text: &str) {
+        let font = self.font;
+        let advance = font::char_advance(size);
+
+        let mut x = pos.x;
+        for c in text.chars() {
+            if let Some(shape) = font.glyph(c) {
+                let transform = Similarity2::new(Vector2::new(x, pos.y), 0.0, size);
+                self.draw_shape(&transform, color, BlendMode::Alpha, shape);
+            }
+
+            x += advance;
+        }
     }
 }
 
@@ -387,6 +460,95 @@ fn match_key(glutin_key: VirtualKeyCode) -> Option<events::Key> {
         VirtualKeyCode::Slash => Some(events::Key::Slash),
         VirtualKeyCode::NumpadSubtract => Some(events::Key::Subtract),
         VirtualKeyCode::Tab => Some(events::Key::Tab),
+        VirtualKeyCode::Escape => Some(events::Key::Escape),
+        VirtualKeyCode::F1 => Some(events::Key::F1),
+        VirtualKeyCode::F2 => Some(events::Key::F2),
+        VirtualKeyCode::F3 => Some(events::Key::F3),
+        VirtualKeyCode::F4 => Some(events::Key::F4),
+        VirtualKeyCode::F5 => Some(events::Key::F5),
+        VirtualKeyCode::F6 => Some(events::Key::F6),
+        VirtualKeyCode::F7 => Some(events::Key::F7),
+        VirtualKeyCode::F8 => Some(events::Key::F8),
+        VirtualKeyCode::F9 => Some(events::Key::F9),
+        VirtualKeyCode::F10 => Some(events::Key::F10),
+        VirtualKeyCode::F11 => Some(events::Key::F11),
+        VirtualKeyCode::F12 => Some(events::Key::F12),
+        _ => None,
+    }
+}
+
+fn match_gamepad_event(id: usize, gilrs_event: gilrs::EventType) -> Option<events::Event> {
+    match gilrs_event {
+        gilrs::EventType::Connected => Some(events::Event::GamepadConnected { id }),
+        gilrs::EventType::Disconnected => Some(events::Event::GamepadDisconnected { id }),
+
+        gilrs::EventType::ButtonPressed(button, _) => {
+            match_gamepad_button(button).map(|button| events::Event::GamepadButton {
+                id,
+                button,
+                down: true,
+            })
+        }
+        gilrs::EventType::ButtonReleased(button, _) => {
+            match_gamepad_button(button).map(|button| events::Event::GamepadButton {
+                id,
+                button,
+                down: false,
+            })
+        }
+
+        // Triggers are reported as buttons with a value on most platforms.
+        gilrs::EventType::ButtonChanged(gilrs::Button::LeftTrigger2, value, _) => {
+            Some(events::Event::GamepadAxis {
+                id,
+                axis: events::GamepadAxis::LeftTrigger,
+                value,
+            })
+        }
+        gilrs::EventType::ButtonChanged(gilrs::Button::RightTrigger2, value, _) => {
+            Some(events::Event::GamepadAxis {
+                id,
+                axis: events::GamepadAxis::RightTrigger,
+                value,
+            })
+        }
+
+        gilrs::EventType::AxisChanged(axis, value, _) => {
+            match_gamepad_axis(axis).map(|axis| events::Event::GamepadAxis { id, axis, value })
+        }
+
+        _ => None,
+    }
+}
+
+fn match_gamepad_button(gilrs_button: gilrs::Button) -> Option<events::GamepadButton> {
+    match gilrs_button {
+        gilrs::Button::South => Some(events::GamepadButton::South),
+        gilrs::Button::East => Some(events::GamepadButton::East),
+        gilrs::Button::North => Some(events::GamepadButton::North),
+        gilrs::Button::West => Some(events::GamepadButton::West),
+        gilrs::Button::LeftTrigger => Some(events::GamepadButton::LeftBumper),
+        gilrs::Button::RightTrigger => Some(events::GamepadButton::RightBumper),
+        gilrs::Button::Select => Some(events::GamepadButton::Select),
+        gilrs::Button::Start => Some(events::GamepadButton::Start),
+        gilrs::Button::LeftThumb => Some(events::GamepadButton::LeftThumb),
+        gilrs::Button::RightThumb => Some(events::GamepadButton::RightThumb),
+        gilrs::Button::DPadUp => Some(events::GamepadButton::DPadUp),
+        gilrs::Button::DPadDown => Some(events::GamepadButton::DPadDown),
+        gilrs::Button::DPadLeft => Some(events::GamepadButton::DPadLeft),
+        gilrs::Button::DPadRight => Some(events::GamepadButton::DPadRight),
+        _ => None,
+    }
+}
+
+fn match_gamepad_axis(gilrs_axis: gilrs::Axis) -> Option<events::GamepadAxis> {
+    match gilrs_axis {
+        gilrs::Axis::LeftStickX => Some(events::GamepadAxis::LeftStickX),
+        gilrs::Axis::LeftStickY => Some(events::GamepadAxis::LeftStickY),
+        gilrs::Axis::RightStickX => Some(events::GamepadAxis::RightStickX),
+        gilrs::Axis::RightStickY => Some(events::GamepadAxis::RightStickY),
+        gilrs::Axis::LeftZ => Some(events::GamepadAxis::LeftTrigger),
+        gilrs::Axis::RightZ => Some(events::GamepadAxis::RightTrigger),
         _ => None,
     }
 }
@@ -1,3 +1,4 @@
+use crate::blend::BlendMode;
 use crate::color::Color;
 use crate::model_transform::ModelTransform;
 use crate::vertex::Vertex;
@@ -20,9 +21,16 @@ impl fmt::Debug for Shape {
     }
 }
 
+/// Draw requests for a shape, waiting for the next frame.
+#[derive(Default)]
+pub(crate) struct Instances {
+    pub(crate) transforms: Vec<ModelTransform>,
+    pub(crate) colors: Vec<Color>,
+}
+
 pub(crate) struct ShapeData {
-    pub(crate) instance_transforms: Vec<ModelTransform>,
-    pub(crate) instance_colors: Vec<Color>,
+    // Indexed by BlendMode::index.
+    pub(crate) instances: [Instances; BlendMode::ALL.len()],
 
     pub(crate) vertex_buffer: Buffer,
     pub(crate) index_buffer: Buffer,
@@ -45,8 +53,7 @@ impl ShapeData {
         let num_indices = indices.len() as u32;
 
         Self {
-            instance_transforms: Vec::new(),
-            instance_colors: Vec::new(),
+            instances: Default::default(),
 
             vertex_buffer,
             index_buffer,
@@ -1,4 +1,5 @@
 use crate::vertex::Vertex;
+use nalgebra::{Point2, Vector2};
 use std::f32;
 
 pub fn build_circle(radius: f32, num_vertices: usize) -> (Vec<Vertex>, Vec<u16>) {
@@ -45,10 +46,112 @@ pub fn build_circle(radius: f32, num_vertices: usize) -> (Vec<Vertex>, Vec<u16>)
     }
 }
 
+/// Builds a quad for each line segment.  Each quad is extended by half the
+/// thickness past both end points, so that segments sharing an end point join
+/// without a gap.
+pub fn build_line_segments(
+    segments: &[(Point2<f32>, Point2<f32>)],
+    thickness: f32,
+) -> (Vec<Vertex>, Vec<u16>) {
+    let half_thickness = 0.5 * thickness;
+
+    let mut verts = Vec::with_capacity(segments.len() * 4);
+    let mut indices = Vec::with_capacity(segments.len() * 6);
+
+    for (start, end) in segments {
+        let delta = end - start;
+        let dir = if delta.norm() > f32::EPSILON {
+            delta.normalize()
+        } else {
+            Vector2::x()
+        };
+        let normal = Vector2::new(-dir.y, dir.x);
+
+        let start = start - dir * half_thickness;
+        let end = end + dir * half_thickness;
+
+        let first = verts.len() as u16;
+        for p in &[
+            start - normal * half_thickness,
+            end - normal * half_thickness,
+            end + normal * half_thickness,
+            start + normal * half_thickness,
+        ] {
+            verts.push(Vertex::new(p.x, p.y));
+        }
+
+        indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    (verts, indices)
+}
+
+/// Builds a thick closed outline through points, in order.  Corners are
+/// mitered, but sharp corners are limited to miter_limit times half the
+/// thickness, so they do not turn into long spikes.
+pub fn build_outline(
+    points: &[Point2<f32>],
+    thickness: f32,
+    miter_limit: f32,
+) -> (Vec<Vertex>, Vec<u16>) {
+    let half_thickness = 0.5 * thickness;
+    let num_points = points.len();
+
+    let mut verts = Vec::with_capacity(num_points * 2);
+    let mut indices = Vec::with_capacity(num_points * 6);
+
+    let direction = |start: &Point2<f32>, end: &Point2<f32>| {
+        let delta = end - start;
+        if delta.norm() > f32::EPSILON {
+            delta.normalize()
+        } else {
+            Vector2::x()
+        }
+    };
+
+    for i in 0..num_points {
+        let prev = &points[(i + num_points - 1) % num_points];
+        let current = &points[i];
+        let next = &points[(i + 1) % num_points];
+
+        let prev_dir = direction(prev, current);
+        let next_dir = direction(current, next);
+        let prev_normal = Vector2::new(-prev_dir.y, prev_dir.x);
+        let next_normal = Vector2::new(-next_dir.y, next_dir.x);
+
+        // The miter points halfway between the normals of the two edges,
+        // and is long enough to keep both edges the full thickness.
+        let miter_sum = prev_normal + next_normal;
+        let miter = if miter_sum.norm() > f32::EPSILON {
+            miter_sum.normalize()
+        } else {
+            prev_normal
+        };
+        let miter_length = half_thickness / miter.dot(&prev_normal).max(1.0 / miter_limit);
+
+        let left = current + miter * miter_length;
+        let right = current - miter * miter_length;
+        verts.push(Vertex::new(left.x, left.y));
+        verts.push(Vertex::new(right.x, right.y));
+    }
+
+    for i in 0..num_points {
+        let left = (2 * i) as u16;
+        let right = left + 1;
+        let next_left = (2 * ((i + 1) % num_points)) as u16;
+        let next_right = next_left + 1;
+
+        indices.extend_from_slice(&[right, next_right, next_left, right, next_left, left]);
+    }
+
+    (verts, indices)
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::super::vertex::Vertex;
+    use nalgebra::Point2;
     use std::f32;
 
     macro_rules! assert_nearly_eq {
@@ -133,4 +236,94 @@ mod tests {
         assert_eq!(verts.len(), 1);
         assert_eq!(indices.len(), 1);
     }
+
+    fn triangle_area(v0: Vertex, v1: Vertex, v2: Vertex) -> f32 {
+        let ax = v1.position[0] - v0.position[0];
+        let ay = v1.position[1] - v0.position[1];
+        let bx = v2.position[0] - v0.position[0];
+        let by = v2.position[1] - v0.position[1];
+
+        0.5 * (ax * by - ay * bx)
+    }
+
+    #[test]
+    fn build_line_segments_counter_clockwise() {
+        // The pipeline culls back faces, so every triangle must be
+        // counter-clockwise no matter which way the segment points.
+        let segments = [
+            (Point2::new(0.0, 0.0), Point2::new(1.0, 0.0)),
+            (Point2::new(1.0, 0.0), Point2::new(1.0, 1.0)),
+            (Point2::new(1.0, 1.0), Point2::new(-1.0, -0.5)),
+        ];
+        let thickness = 0.1;
+
+        let (verts, indices) = super::build_line_segments(&segments, thickness);
+
+        assert_eq!(verts.len(), segments.len() * 4);
+        assert_eq!(indices.len(), segments.len() * 6);
+
+        for triangle in indices.chunks(3) {
+            let area = triangle_area(
+                verts[triangle[0] as usize],
+                verts[triangle[1] as usize],
+                verts[triangle[2] as usize],
+            );
+            assert!(area > 0.0, "assertion failed: {} > 0", area);
+        }
+
+        // The first segment is 1.0 long, plus half the thickness at either end.
+        let length = vertex_distance(verts[0], verts[1]);
+        assert_nearly_eq!(length, 1.0 + thickness, 1e-6);
+    }
+
+    #[test]
+    fn build_outline_square() {
+        let points = [
+            Point2::new(-1.0, -1.0),
+            Point2::new(1.0, -1.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(-1.0, 1.0),
+        ];
+        let thickness = 0.2;
+
+        let (verts, indices) = super::build_outline(&points, thickness, 4.0);
+
+        assert_eq!(verts.len(), points.len() * 2);
+        assert_eq!(indices.len(), points.len() * 6);
+
+        for triangle in indices.chunks(3) {
+            let area = triangle_area(
+                verts[triangle[0] as usize],
+                verts[triangle[1] as usize],
+                verts[triangle[2] as usize],
+            );
+            assert!(area > 0.0, "assertion failed: {} > 0", area);
+        }
+
+        // Square corners are mitered out to the corners of the inner and outer squares.
+        let origin = Vertex::new(0.0, 0.0);
+        let inner = vertex_distance(verts[0], origin);
+        let outer = vertex_distance(verts[1], origin);
+        assert_nearly_eq!(inner, f32::sqrt(2.0) * (1.0 - 0.5 * thickness), 1e-6);
+        assert_nearly_eq!(outer, f32::sqrt(2.0) * (1.0 + 0.5 * thickness), 1e-6);
+    }
+
+    #[test]
+    fn build_outline_limits_sharp_corners() {
+        let points = [
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(0.0, 0.01),
+        ];
+        let thickness = 0.1;
+        let miter_limit = 3.0;
+
+        let (verts, _) = super::build_outline(&points, thickness, miter_limit);
+
+        for (i, point) in points.iter().enumerate() {
+            let point = Vertex::new(point.x, point.y);
+            let miter_length = vertex_distance(verts[2 * i], point);
+            assert!(miter_length <= miter_limit * 0.5 * thickness + 1e-6);
+        }
+    }
 }
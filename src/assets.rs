@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Overrides where the assets are loaded from.
+const ASSETS_DIR_VAR: &str = "ASTEROIDS_ASSETS";
+
+/// Only assets directories holding this file are ours, so that some other
+/// program's assets directory further up is not picked by mistake.
+const MARKER_FILE: &str = "waves.ron";
+
+/// The assets directory, from ASTEROIDS_ASSETS if it is set. Otherwise it is
+/// the first of our assets directories found beside the executable or in one of its
+/// parents, so it is found both in an install and in a cargo target directory.
+pub fn assets_dir() -> PathBuf {
+    if let Some(dir) = env::var_os(ASSETS_DIR_VAR) {
+        return PathBuf::from(dir);
+    }
+
+    env::current_exe()
+        .ok()
+        .and_then(|exe| find_assets_dir(&exe))
+        .unwrap_or_else(|| PathBuf::from("assets"))
+}
+
+fn find_assets_dir(exe: &Path) -> Option<PathBuf> {
+    exe.ancestors()
+        .skip(1)
+        .map(|dir| dir.join("assets"))
+        .find(|dir| dir.join(MARKER_FILE).is_file())
+}
+
+/// Loads a RON file from the assets directory.
+pub fn load_ron<T: DeserializeOwned>(relative_path: &str) -> Result<T> {
+    let path = assets_dir().join(relative_path);
+
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let value =
+        ron::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    Ok(value)
+}
+
+/// A hash of value's RON form. Replays record these, to check that they
+/// are played back with the same assets they were recorded with.
+pub fn hash_ron<T: Serialize>(value: &T) -> u64 {
+    let text = ron::to_string(value).expect("assets can always be serialized");
+
+    // FNV-1a, which unlike the std hashers is the same on every build.
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_assets_dir, MARKER_FILE};
+    use std::fs;
+    use std::process;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn finds_assets_above_executable() {
+        // Unique, so that simultaneous test runs do not share the directory.
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root =
+            std::env::temp_dir().join(format!("asteroids_assets_test_{}_{}", process::id(), nanos));
+        let exe_dir = root.join("target").join("debug");
+        fs::create_dir_all(&exe_dir).unwrap();
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::write(root.join("assets").join(MARKER_FILE), "").unwrap();
+
+        // Closer to the executable, but not ours.
+        fs::create_dir_all(root.join("target").join("assets")).unwrap();
+
+        let found = find_assets_dir(&exe_dir.join("asteroids"));
+        assert_eq!(found, Some(root.join("assets")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+use std::f32;
+
+use rand::Rng;
+use specs::shrev::{EventChannel, ReaderId};
+use specs::{
+    Builder, Component, Entities, Entity, LazyUpdate, Read, ReadStorage, System, SystemData,
+    VecStorage, World, Write, WriteStorage,
+};
+
+use crate::na::{Isometry2, Translation2, UnitComplex, Vector2};
+
+use crate::bullet::Bullet;
+use crate::lives::Invulnerable;
+use crate::particles::{ParticleParams, Particles};
+use crate::physics::{CollisionEvent, Destroy, Physical};
+use crate::player::{HyperspaceJump, Player};
+use crate::prefab::{Overrides, Prefabs};
+use crate::rng::GameRng;
+use crate::saucer::{Saucer, SaucerBullet};
+use crate::score::Score;
+use crate::sound::{SoundEvent, SoundQueue};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AsteroidSize {
+    Large,
+    Medium,
+    Small,
+}
+
+impl AsteroidSize {
+    /// Name of the prefab that asteroids of this size are built from.
+    pub fn prefab_name(self) -> &'static str {
+        match self {
+            AsteroidSize::Large => "large_asteroid",
+            AsteroidSize::Medium => "medium_asteroid",
+            AsteroidSize::Small => "small_asteroid",
+        }
+    }
+
+    /// Every size, from largest to smallest.
+    pub const ALL: [AsteroidSize; 3] = [
+        AsteroidSize::Large,
+        AsteroidSize::Medium,
+        AsteroidSize::Small,
+    ];
+
+    /// Rough radius of asteroids of this size, taken from their prefab.
+    pub fn scale(self, prefabs: &Prefabs) -> f32 {
+        prefabs
+            .get(self.prefab_name())
+            .ok()
+            .and_then(|prefab| prefab.asteroid_scale())
+            .expect("asteroid prefab does not have an Asteroid shape")
+    }
+
+    /// Points scored for destroying an asteroid of this size.
+    pub fn points(self) -> u32 {
+        match self {
+            AsteroidSize::Large => 20,
+            AsteroidSize::Medium => 50,
+            AsteroidSize::Small => 100,
+        }
+    }
+
+    /// The size of the pieces this asteroid breaks into, if any.
+    pub fn smaller(self) -> Option<AsteroidSize> {
+        match self {
+            AsteroidSize::Large => Some(AsteroidSize::Medium),
+            AsteroidSize::Medium => Some(AsteroidSize::Small),
+            AsteroidSize::Small => None,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct Asteroid {
+    size: AsteroidSize,
+}
+
+impl Asteroid {
+    pub fn new(size: AsteroidSize) -> Self {
+        Asteroid { size }
+    }
+
+    pub fn size(&self) -> AsteroidSize {
+        self.size
+    }
+}
+
+/// Adds all of the components for an asteroid to the builder.
+pub fn build_asteroid<B: Builder, R: Rng>(
+    builder: B,
+    prefabs: &Prefabs,
+    rng: &mut R,
+    size: AsteroidSize,
+    pos: Isometry2<f32>,
+    vel: Vector2<f32>,
+) -> Entity {
+    // Smaller asteroids spin faster.
+    let max_spin = 0.05 / size.scale(prefabs);
+    let spin = rng.gen_range(-max_spin..max_spin);
+    let overrides = Overrides::at(pos)
+        .with_velocity(vel)
+        .with_spin(spin)
+        .with_shape_seed(rng.gen());
+
+    prefabs
+        .get(size.prefab_name())
+        .expect("asteroid prefab is missing")
+        .apply(builder, overrides)
+        .with(Asteroid::new(size))
+        .build()
+}
+
+pub struct AsteroidSplitter {
+    reader: Option<ReaderId<CollisionEvent>>,
+
+    // Speed added to each piece, directed away from the impact.
+    split_speed: f32,
+    // Total angle that the pieces are spread across.
+    split_spread: f32,
+
+    explosion: ParticleParams,
+    // Explosion particles per unit of asteroid scale.
+    explosion_density: f32,
+    ship_explosion_particles: u32,
+}
+
+impl AsteroidSplitter {
+    pub fn new() -> Self {
+        AsteroidSplitter {
+            reader: None,
+
+            split_speed: 0.15,
+            split_spread: 0.5 * f32::consts::PI,
+
+            explosion: ParticleParams::explosion(),
+            explosion_density: 500.0,
+            ship_explosion_particles: 120,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn split<R: Rng>(
+        &self,
+        lazy: &LazyUpdate,
+        entities: &Entities,
+        prefabs: &Prefabs,
+        rng: &mut R,
+        size: AsteroidSize,
+        asteroid_physical: &Physical,
+        impact_physical: &Physical,
+    ) {
+        let smaller_size = match size.smaller() {
+            Some(smaller_size) => smaller_size,
+            None => return,
+        };
+
+        let asteroid_pos = asteroid_physical.position().translation.vector;
+        let impact_pos = impact_physical.position().translation.vector;
+
+        // Spread the pieces out around the direction pointing from
+        // the impact through the asteroid.
+        let away = asteroid_pos - impact_pos;
+        let away_angle = if away.norm() > f32::EPSILON {
+            away.y.atan2(away.x)
+        } else {
+            rng.gen_range(0.0..2.0 * f32::consts::PI)
+        };
+
+        let num_pieces = rng.gen_range(2..=3);
+        for i in 0..num_pieces {
+            let fraction = i as f32 / (num_pieces - 1) as f32 - 0.5;
+            let jitter = rng.gen_range(-0.1..0.1);
+            let angle = away_angle + self.split_spread * (fraction + jitter);
+            let direction = Vector2::new(angle.cos(), angle.sin());
+
+            let pos = Isometry2::from_parts(
+                Translation2::from(asteroid_pos + smaller_size.scale(prefabs) * direction),
+                UnitComplex::new(rng.gen_range(0.0..2.0 * f32::consts::PI)),
+            );
+            let vel = asteroid_physical.velocity() + self.split_speed * direction;
+
+            build_asteroid(
+                lazy.create_entity(entities),
+                prefabs,
+                rng,
+                smaller_size,
+                pos,
+                vel,
+            );
+        }
+    }
+}
+
+impl<'a> System<'a> for AsteroidSplitter {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, EventChannel<CollisionEvent>>,
+        Read<'a, LazyUpdate>,
+        Read<'a, Prefabs>,
+        Write<'a, Score>,
+        Write<'a, GameRng>,
+        Write<'a, SoundQueue>,
+        Write<'a, Particles>,
+        Entities<'a>,
+        ReadStorage<'a, Asteroid>,
+        ReadStorage<'a, Bullet>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Saucer>,
+        ReadStorage<'a, SaucerBullet>,
+        ReadStorage<'a, Invulnerable>,
+        ReadStorage<'a, HyperspaceJump>,
+        ReadStorage<'a, Physical>,
+        WriteStorage<'a, Destroy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            collision_events,
+            lazy,
+            prefabs,
+            mut score,
+            mut rng,
+            mut sounds,
+            mut particles,
+            entities,
+            asteroids,
+            bullets,
+            players,
+            saucers,
+            saucer_bullets,
+            invulnerables,
+            jumps,
+            physicals,
+            mut destroys,
+        ) = data;
+
+        // Asteroids are destroyed by bullets, saucers, and ships that are neither
+        // invulnerable nor in hyperspace.
+        // Both the asteroid and whatever hit it are destroyed.
+        let is_impactor = |e: Entity| {
+            bullets.contains(e)
+                || saucers.contains(e)
+                || (players.contains(e) && !invulnerables.contains(e) && !jumps.contains(e))
+        };
+        // Only the player scores points.
+        let is_scoring = |e: Entity| !saucers.contains(e) && !saucer_bullets.contains(e);
+
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("AsteroidSplitter::setup was not called");
+        let events: Vec<CollisionEvent> = collision_events.read(reader).cloned().collect();
+
+        for event in events {
+            let (asteroid_entity, impactor_entity) =
+                if asteroids.contains(event.entity_a) && is_impactor(event.entity_b) {
+                    (event.entity_a, event.entity_b)
+                } else if asteroids.contains(event.entity_b) && is_impactor(event.entity_a) {
+                    (event.entity_b, event.entity_a)
+                } else {
+                    continue;
+                };
+
+            // A bullet can touch several asteroids in one frame, and an asteroid
+            // can be hit by several bullets. Only the first contact counts.
+            if destroys.contains(asteroid_entity) || destroys.contains(impactor_entity) {
+                continue;
+            }
+
+            if let (Some(asteroid), Some(asteroid_physical), Some(impactor_physical)) = (
+                asteroids.get(asteroid_entity),
+                physicals.get(asteroid_entity),
+                physicals.get(impactor_entity),
+            ) {
+                if is_scoring(impactor_entity) {
+                    score.points += asteroid.size().points();
+                }
+
+                sounds.push(SoundEvent::Explosion(asteroid.size()));
+                let count = (self.explosion_density * asteroid.size().scale(&prefabs)) as u32;
+                particles.burst(
+                    &self.explosion,
+                    count,
+                    asteroid_physical.position().translation.vector,
+                );
+
+                if players.contains(impactor_entity) || saucers.contains(impactor_entity) {
+                    sounds.push(SoundEvent::ShipExplosion);
+                    particles.burst(
+                        &self.explosion,
+                        self.ship_explosion_particles,
+                        impactor_physical.position().translation.vector,
+                    );
+                }
+
+                self.split(
+                    &lazy,
+                    &entities,
+                    &prefabs,
+                    &mut *rng,
+                    asteroid.size(),
+                    asteroid_physical,
+                    impactor_physical,
+                );
+            }
+
+            destroys
+                .insert(asteroid_entity, Destroy)
+                .expect("failed to destroy asteroid");
+            destroys
+                .insert(impactor_entity, Destroy)
+                .expect("failed to destroy impactor");
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(
+            world
+                .fetch_mut::<EventChannel<CollisionEvent>>()
+                .register_reader(),
+        );
+    }
+}
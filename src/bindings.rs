@@ -0,0 +1,325 @@
+use anyhow::{Context, Result};
+use graphics::events::{Event, GamepadAxis, GamepadButton, Key};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::input::{Action, Actions};
+
+const DEFAULT_BINDINGS: &str = include_str!("../assets/bindings.ron");
+
+/// Keys the app handles while playing or paused, so they cannot be bound to actions.
+/// Escape is also kept back, since it cancels rebinding.
+pub const RESERVED_KEYS: [Key; 4] = [Key::P, Key::Q, Key::C, Key::F12];
+
+/// The keys bound to each action.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyBindings {
+    bindings: BTreeMap<Action, Vec<Key>>,
+}
+
+impl KeyBindings {
+    /// Loads the player's bindings, or the defaults if they have not changed any.
+    pub fn load() -> Result<Self> {
+        let path = bindings_path()?;
+        if !path.exists() {
+            return Ok(KeyBindings::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut bindings: KeyBindings = ron::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        bindings.add_missing(&KeyBindings::default());
+
+        Ok(bindings)
+    }
+
+    /// Gives actions that have no entry, such as ones added since the bindings
+    /// were saved, the default keys that are not already in use.
+    fn add_missing(&mut self, defaults: &KeyBindings) {
+        for (&action, default_keys) in &defaults.bindings {
+            if self.bindings.contains_key(&action) {
+                continue;
+            }
+
+            let keys = default_keys
+                .iter()
+                .copied()
+                .filter(|key| !self.bindings.values().any(|keys| keys.contains(key)))
+                .collect();
+            self.bindings.insert(action, keys);
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = bindings_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?;
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.bindings
+            .get(&action)
+            .map(|keys| keys.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Makes key the only key for action. Any other action it was bound to loses it.
+    pub fn rebind(&mut self, action: Action, key: Key) {
+        for keys in self.bindings.values_mut() {
+            keys.retain(|&k| k != key);
+        }
+
+        self.bindings.insert(action, vec![key]);
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        ron::from_str(DEFAULT_BINDINGS).expect("built in bindings.ron is invalid")
+    }
+}
+
+fn bindings_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context("No config directory for this user")?;
+    Ok(config_dir.join("asteroids").join("bindings.ron"))
+}
+
+// Stick movement smaller than this is ignored, since sticks rarely rest exactly at zero.
+const DEAD_ZONE: f32 = 0.2;
+
+/// Current state of one gamepad.
+#[derive(Default)]
+struct Gamepad {
+    axes: HashMap<GamepadAxis, f32>,
+    held: HashSet<GamepadButton>,
+}
+
+impl Gamepad {
+    fn axis(&self, axis: GamepadAxis) -> f32 {
+        let value = self.axes.get(&axis).copied().unwrap_or(0.0);
+        if value.abs() < DEAD_ZONE {
+            0.0
+        } else {
+            // Rescale so movement starts from zero at the edge of the dead zone.
+            value.signum() * (value.abs() - DEAD_ZONE) / (1.0 - DEAD_ZONE)
+        }
+    }
+
+    /// Left stick turns and thrusts, right stick strafes, the
+    /// south button or right trigger fires, and the north button jumps.
+    fn add_to(&self, actions: &mut Actions) {
+        actions.thrust += self.axis(GamepadAxis::LeftStickY).max(0.0);
+        actions.turn += self.axis(GamepadAxis::LeftStickX);
+        actions.strafe += self.axis(GamepadAxis::RightStickX);
+
+        actions.fire |=
+            self.held.contains(&GamepadButton::South) || self.axis(GamepadAxis::RightTrigger) > 0.5;
+        actions.hyperspace |= self.held.contains(&GamepadButton::North);
+    }
+}
+
+/// Turns key presses and gamepad input into actions, using the current bindings.
+pub struct Controls {
+    bindings: KeyBindings,
+    held: HashSet<Key>,
+    gamepads: HashMap<usize, Gamepad>,
+}
+
+impl Controls {
+    pub fn new(bindings: KeyBindings) -> Self {
+        Controls {
+            bindings,
+            held: HashSet::new(),
+            gamepads: HashMap::new(),
+        }
+    }
+
+    pub fn bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+    pub fn rebind(&mut self, action: Action, key: Key) {
+        self.bindings.rebind(action, key);
+    }
+
+    pub fn key_changed(&mut self, key: Key, down: bool) {
+        if down {
+            self.held.insert(key);
+        } else {
+            self.held.remove(&key);
+        }
+    }
+
+    /// Tracks gamepad events. Returns false for other events.
+    pub fn gamepad_event(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::GamepadConnected { id } => {
+                self.gamepads.insert(id, Gamepad::default());
+            }
+            Event::GamepadDisconnected { id } => {
+                self.gamepads.remove(&id);
+            }
+            Event::GamepadButton { id, button, down } => {
+                let gamepad = self.gamepads.entry(id).or_default();
+                if down {
+                    gamepad.held.insert(button);
+                } else {
+                    gamepad.held.remove(&button);
+                }
+            }
+            Event::GamepadAxis { id, axis, value } => {
+                self.gamepads
+                    .entry(id)
+                    .or_default()
+                    .axes
+                    .insert(axis, value);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// An action is on while any of its keys are held.
+    /// Every gamepad's sticks are added on top.
+    pub fn actions(&self) -> Actions {
+        let mut actions = Actions::default();
+        for &action in &Action::ALL {
+            let on = self
+                .bindings
+                .keys(action)
+                .iter()
+                .any(|key| self.held.contains(key));
+            actions.set(action, on);
+        }
+
+        for gamepad in self.gamepads.values() {
+            gamepad.add_to(&mut actions);
+        }
+        actions.thrust = actions.thrust.min(1.0);
+        actions.strafe = actions.strafe.clamp(-1.0, 1.0);
+        actions.turn = actions.turn.clamp(-1.0, 1.0);
+
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Controls, KeyBindings, RESERVED_KEYS};
+    use crate::input::Action;
+    use graphics::events::{Event, GamepadAxis, GamepadButton, Key};
+
+    #[test]
+    fn default_keys_can_be_rebound() {
+        let defaults = KeyBindings::default();
+        let mut controls = Controls::new(KeyBindings::default());
+        controls.rebind(Action::Fire, Key::W);
+
+        for &action in Action::ALL.iter() {
+            let key = defaults.keys(action)[0];
+            assert!(!RESERVED_KEYS.contains(&key), "{:?} is reserved", key);
+            assert_ne!(key, Key::Escape);
+            controls.rebind(action, key);
+        }
+
+        for &action in Action::ALL.iter() {
+            assert_eq!(
+                controls.bindings().keys(action),
+                &defaults.keys(action)[..1]
+            );
+        }
+    }
+
+    #[test]
+    fn any_bound_key_holds_action() {
+        let mut controls = Controls::new(KeyBindings::default());
+
+        controls.key_changed(Key::W, true);
+        controls.key_changed(Key::Up, true);
+        controls.key_changed(Key::W, false);
+        assert!(controls.actions().accel_forward);
+
+        controls.key_changed(Key::Up, false);
+        assert!(!controls.actions().accel_forward);
+    }
+
+    #[test]
+    fn rebinding_moves_key() {
+        let mut controls = Controls::new(KeyBindings::default());
+        controls.rebind(Action::Fire, Key::W);
+
+        assert_eq!(controls.bindings().keys(Action::Fire), &[Key::W]);
+        assert!(!controls
+            .bindings()
+            .keys(Action::AccelForward)
+            .contains(&Key::W));
+
+        controls.key_changed(Key::W, true);
+        assert!(controls.actions().fire);
+        assert!(!controls.actions().accel_forward);
+    }
+
+    #[test]
+    fn missing_actions_get_unused_defaults() {
+        let mut bindings: KeyBindings =
+            ron::from_str("(bindings: { Fire: [H], TurnLeft: [Left] })").unwrap();
+        bindings.add_missing(&KeyBindings::default());
+
+        assert_eq!(bindings.keys(Action::Fire), &[Key::H]);
+        assert_eq!(bindings.keys(Action::Hyperspace), &[Key::LShift]);
+        assert_eq!(bindings.keys(Action::AccelForward), &[Key::W, Key::Up]);
+    }
+
+    #[test]
+    fn gamepad_sticks_are_proportional() {
+        let mut controls = Controls::new(KeyBindings::default());
+        let id = 3;
+
+        controls.gamepad_event(&Event::GamepadConnected { id });
+        controls.gamepad_event(&Event::GamepadAxis {
+            id,
+            axis: GamepadAxis::LeftStickY,
+            value: 0.6,
+        });
+        controls.gamepad_event(&Event::GamepadAxis {
+            id,
+            axis: GamepadAxis::LeftStickX,
+            value: -1.0,
+        });
+        controls.gamepad_event(&Event::GamepadButton {
+            id,
+            button: GamepadButton::South,
+            down: true,
+        });
+
+        let actions = controls.actions();
+        assert!((actions.thrust_amount() - 0.5).abs() < 1e-5);
+        assert!((actions.turn_amount() + 1.0).abs() < 1e-5);
+        assert!(actions.fire);
+
+        // Inside the dead zone.
+        controls.gamepad_event(&Event::GamepadAxis {
+            id,
+            axis: GamepadAxis::LeftStickY,
+            value: 0.1,
+        });
+        assert_eq!(controls.actions().thrust_amount(), 0.0);
+
+        controls.gamepad_event(&Event::GamepadDisconnected { id });
+        let actions = controls.actions();
+        assert_eq!(actions.turn_amount(), 0.0);
+        assert!(!actions.fire);
+    }
+}
@@ -0,0 +1,41 @@
+use specs::{Component, Entities, Join, Read, System, VecStorage, WriteStorage};
+
+use crate::input::Input;
+use crate::physics::Destroy;
+
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct Bullet {
+    lifetime: f32,
+}
+
+impl Bullet {
+    pub fn new(lifetime: f32) -> Self {
+        Bullet { lifetime }
+    }
+}
+
+pub struct BulletLifetime;
+
+impl<'a> System<'a> for BulletLifetime {
+    type SystemData = (
+        Read<'a, Input>,
+        Entities<'a>,
+        WriteStorage<'a, Bullet>,
+        WriteStorage<'a, Destroy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (input, entities, mut bullets, mut destroys) = data;
+
+        for (e, bullet) in (&entities, &mut bullets).join() {
+            bullet.lifetime -= input.frame_time;
+
+            if bullet.lifetime <= 0.0 {
+                destroys
+                    .insert(e, Destroy)
+                    .expect("failed to destroy expired bullet");
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+
+use crate::assets;
+
+const DEFAULT_CLOCK: &str = include_str!("../assets/physics.ron");
+
+/// How bodies are positioned for rendering between physics steps.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum RenderBlend {
+    Interpolate,
+    Extrapolate,
+}
+
+/// Fixed timestep clock for the physics world.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PhysicsClock {
+    step_rate: f32,
+    max_steps: u32,
+    blend: RenderBlend,
+
+    // Time that has passed, but has not been stepped yet.
+    #[serde(skip)]
+    accumulator: f32,
+}
+
+impl PhysicsClock {
+    pub fn new(step_rate: f32, max_steps: u32, blend: RenderBlend) -> Self {
+        PhysicsClock {
+            step_rate,
+            max_steps,
+            blend,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn load() -> Result<Self> {
+        let clock: PhysicsClock = assets::load_ron("physics.ron")?;
+        ensure!(
+            clock.step_rate > 0.0,
+            "physics.ron step_rate must be positive"
+        );
+        ensure!(
+            clock.max_steps > 0,
+            "physics.ron max_steps must be positive"
+        );
+
+        Ok(clock)
+    }
+
+    pub fn step_rate(&self) -> f32 {
+        self.step_rate
+    }
+
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    pub fn blend(&self) -> RenderBlend {
+        self.blend
+    }
+
+    pub fn step_time(&self) -> f32 {
+        1.0 / self.step_rate
+    }
+
+    /// Adds frame_time to the clock, and returns the number of steps to take.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time;
+
+        let steps = (self.accumulator * self.step_rate).floor() as u32;
+
+        // If we are going to execute too many steps, drop the extra time.
+        if steps > self.max_steps {
+            self.accumulator = 0.0;
+            self.max_steps
+        } else {
+            self.accumulator = (self.accumulator - steps as f32 * self.step_time()).max(0.0);
+            steps
+        }
+    }
+
+    /// How far along to draw bodies, where 0 is the previous step and 1 is the current one.
+    pub fn blend_factor(&self) -> f32 {
+        let step_fraction = (self.accumulator * self.step_rate).min(1.0);
+
+        match self.blend {
+            RenderBlend::Interpolate => step_fraction,
+            RenderBlend::Extrapolate => 1.0 + step_fraction,
+        }
+    }
+}
+
+impl Default for PhysicsClock {
+    fn default() -> Self {
+        ron::from_str(DEFAULT_CLOCK).expect("built in physics.ron is invalid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PhysicsClock, RenderBlend};
+
+    #[test]
+    fn carries_partial_steps() {
+        let mut clock = PhysicsClock::default();
+        clock.blend = RenderBlend::Interpolate;
+        let step_time = clock.step_time();
+
+        assert_eq!(clock.advance(0.5 * step_time), 0);
+        assert!((clock.blend_factor() - 0.5).abs() < 1e-3);
+
+        assert_eq!(clock.advance(step_time), 1);
+        assert!((clock.blend_factor() - 0.5).abs() < 1e-3);
+
+        clock.blend = RenderBlend::Extrapolate;
+        assert!((clock.blend_factor() - 1.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn drops_time_past_max_steps() {
+        let mut clock = PhysicsClock::default();
+        let step_time = clock.step_time();
+
+        assert_eq!(clock.advance(100.0 * step_time), clock.max_steps);
+        assert_eq!(clock.advance(0.5 * step_time), 0);
+    }
+}
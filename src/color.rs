@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// A color in the simulation. The renderer converts it to a graphics color,
+/// so that the game does not depend on the graphics crate.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r, g, b, a }
+    }
+}
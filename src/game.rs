@@ -0,0 +1,480 @@
+use specs::shred::FetchMut;
+use specs::{Dispatcher, DispatcherBuilder, World, WorldExt};
+use std::time::Duration;
+
+use crate::na;
+use crate::na::{Isometry2, Vector2};
+
+use crate::asteroid::AsteroidSplitter;
+use crate::bullet::BulletLifetime;
+use crate::clock::PhysicsClock;
+use crate::game_state::GameState;
+use crate::input::{Actions, Input};
+use crate::lives::{InvulnerabilityTimer, Respawn};
+use crate::particles::ParticleSystem;
+use crate::physics::{CollisionCreator, Destroyer, Physics};
+use crate::player::PlayerController;
+use crate::prefab::{spawn_prefab, Overrides, Prefabs};
+use crate::renderable::Renderable;
+use crate::rng::GameRng;
+use crate::saucer::{SaucerAi, SaucerCollisions};
+use crate::sound::{Heartbeat, SoundQueue};
+use crate::waves::{WaveManager, WaveTable};
+
+/// The specs world and all of the systems that simulate it.
+/// Does not need a window or graphics device, so it can be stepped headless.
+pub struct Game<'a, 'b> {
+    world: World,
+    dispatcher: Box<Dispatcher<'a, 'b>>,
+}
+
+impl<'a, 'b> Game<'a, 'b> {
+    /// Everything random in the game comes from seed.
+    pub fn new(
+        max_coords: (f32, f32),
+        state: GameState,
+        wave_table: WaveTable,
+        prefabs: Prefabs,
+        physics_clock: PhysicsClock,
+        seed: u64,
+    ) -> Self {
+        let mut world = World::new();
+        // Renderable is not used in a dispatched system, so
+        // we must explicitly register it.
+        world.register::<Renderable>();
+        let mut dispatcher = Box::new(
+            DispatcherBuilder::new()
+                .with(PlayerController::new(max_coords), "player", &[])
+                .with(SaucerAi::new(max_coords), "saucer_ai", &["player"])
+                .with(BulletLifetime, "bullet_lifetime", &[])
+                .with(InvulnerabilityTimer, "invulnerability_timer", &[])
+                .with(CollisionCreator, "collision_creator", &[])
+                .with(
+                    Physics::new(max_coords),
+                    "physics",
+                    &[
+                        "player",
+                        "saucer_ai",
+                        "invulnerability_timer",
+                        "collision_creator",
+                    ],
+                )
+                .with(AsteroidSplitter::new(), "asteroid_splitter", &["physics"])
+                .with(
+                    SaucerCollisions::new(),
+                    "saucer_collisions",
+                    &["asteroid_splitter"],
+                )
+                .with(
+                    Respawn::new(),
+                    "respawn",
+                    &["asteroid_splitter", "saucer_collisions"],
+                )
+                .with(
+                    WaveManager::new(wave_table, max_coords),
+                    "wave_manager",
+                    &["asteroid_splitter", "respawn"],
+                )
+                .with(
+                    ParticleSystem::new(max_coords),
+                    "particles",
+                    &["physics", "asteroid_splitter", "saucer_collisions"],
+                )
+                .with(Heartbeat::new(), "heartbeat", &["wave_manager"])
+                .with(
+                    Destroyer,
+                    "destroyer",
+                    &[
+                        "physics",
+                        "bullet_lifetime",
+                        "asteroid_splitter",
+                        "saucer_ai",
+                        "saucer_collisions",
+                    ],
+                )
+                .build(),
+        );
+        dispatcher.setup(&mut world);
+
+        world.insert(state);
+        world.insert(prefabs);
+        world.insert(physics_clock);
+        world.insert(GameRng::new(seed));
+
+        let player_pos = Isometry2::new(Vector2::new(0.0, 0.0), na::zero());
+        spawn_prefab(
+            &mut world,
+            "player",
+            Overrides::at(player_pos).with_velocity(Vector2::new(0.25, 0.5)),
+        )
+        .expect("player prefab is missing");
+
+        Game { world, dispatcher }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn state(&self) -> GameState {
+        *self.world.read_resource::<GameState>()
+    }
+
+    /// Also releases every action, so that keys held when pausing do not
+    /// stay pressed after resuming.
+    pub fn set_state(&mut self, state: GameState) {
+        *self.world.write_resource::<GameState>() = state;
+        self.input_mut().actions = Actions::default();
+    }
+
+    pub fn input_mut(&mut self) -> FetchMut<Input> {
+        self.world.write_resource::<Input>()
+    }
+
+    /// Advances the simulation by frame_time.
+    pub fn step(&mut self, frame_time: Duration) {
+        self.step_seconds(frame_time.as_secs_f32());
+    }
+
+    /// Advances the simulation by frame_time seconds.
+    /// Replays step with this, so they see exactly the recorded frame times.
+    pub fn step_seconds(&mut self, frame_time: f32) {
+        self.input_mut().frame_time = frame_time;
+
+        // Only this step's sounds are kept, so the queue cannot grow
+        // without bound when nothing drains it, as in headless runs.
+        self.world.write_resource::<SoundQueue>().clear();
+
+        self.dispatcher.dispatch(&self.world);
+        self.world.maintain();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Game;
+    use crate::clock::{PhysicsClock, RenderBlend};
+    use crate::game_state::GameState;
+    use crate::lives::Lives;
+    use crate::na::Vector2;
+    use crate::physics::{Destroy, Physical};
+    use crate::player::{HyperspaceJump, Player};
+    use crate::prefab::Prefabs;
+    use crate::replay::Replay;
+    use crate::saucer::{build_saucer, SaucerBullet, SaucerSize};
+    use crate::score::Score;
+    use crate::waves::{Wave, WaveTable};
+    use specs::{Entity, Join, WorldExt};
+    use std::time::Duration;
+
+    fn positions(game: &Game) -> Vec<Vector2<f32>> {
+        game.world()
+            .read_storage::<Physical>()
+            .join()
+            .map(|physical| physical.position().translation.vector)
+            .collect()
+    }
+
+    fn new_game() -> Game<'static, 'static> {
+        Game::new(
+            (4.0 / 3.0, 1.0),
+            GameState::Playing,
+            WaveTable::default(),
+            Prefabs::default(),
+            PhysicsClock::default(),
+            0,
+        )
+    }
+
+    /// A game where no asteroids ever appear, so only what a test adds can collide.
+    fn game_without_asteroids(seed: u64, prefabs: Prefabs) -> Game<'static, 'static> {
+        let no_asteroids: WaveTable = ron::from_str(
+            "(waves: [(count: 0, min_speed: 0.1, max_speed: 0.1, \
+             sizes: (large: 1.0, medium: 0.0, small: 0.0))], \
+             extra_count: 0, extra_speed: 0.0, max_count: 0)",
+        )
+        .unwrap();
+
+        Game::new(
+            (4.0 / 3.0, 1.0),
+            GameState::Playing,
+            no_asteroids,
+            prefabs,
+            PhysicsClock::default(),
+            seed,
+        )
+    }
+
+    fn player_entity(game: &Game) -> Entity {
+        let world = game.world();
+        (&world.entities(), &world.read_storage::<Player>())
+            .join()
+            .map(|(e, _)| e)
+            .next()
+            .unwrap()
+    }
+
+    fn position_of(game: &Game, e: Entity) -> Option<Vector2<f32>> {
+        game.world()
+            .read_storage::<Physical>()
+            .get(e)
+            .map(|physical| physical.position().translation.vector)
+    }
+
+    /// Adds a large saucer that is ready to fire straight away.
+    fn add_large_saucer(
+        game: &mut Game,
+        pos: Vector2<f32>,
+        waypoints: Vec<Vector2<f32>>,
+    ) -> Entity {
+        let prefabs = Prefabs::default();
+        let size = SaucerSize::Large;
+        let prefab = prefabs.get(size.prefab_name()).unwrap();
+
+        build_saucer(
+            game.world.create_entity(),
+            prefab,
+            size,
+            pos,
+            waypoints,
+            0.0,
+        )
+    }
+
+    /// How far, in radians, a saucer's first shot misses the line to the ship.
+    fn first_shot_error(seed: u64, score: u32) -> f32 {
+        let mut game = game_without_asteroids(seed, Prefabs::default());
+        game.world.write_resource::<Score>().points = score;
+        let player = player_entity(&game);
+        let saucer = add_large_saucer(
+            &mut game,
+            Vector2::new(-1.0, 0.5),
+            vec![Vector2::new(1.0, 0.5)],
+        );
+        let frame_time = Duration::from_secs_f32(1.0 / 60.0);
+
+        for _ in 0..10 {
+            // Where the saucer aims from and at, if it fires during this step.
+            let aim = (position_of(&game, saucer), position_of(&game, player));
+            game.step(frame_time);
+
+            if game.world().read_storage::<SaucerBullet>().count() > 0 {
+                // The bullet only becomes physical on the next step.
+                game.step(frame_time);
+                let world = game.world();
+                let shot = (
+                    &world.read_storage::<SaucerBullet>(),
+                    &world.read_storage::<Physical>(),
+                )
+                    .join()
+                    .map(|(_, physical)| physical.velocity())
+                    .next()
+                    .unwrap();
+
+                let expected = aim.1.unwrap() - aim.0.unwrap();
+                return (shot.y.atan2(shot.x) - expected.y.atan2(expected.x)).abs();
+            }
+        }
+
+        panic!("saucer never fired");
+    }
+
+    #[test]
+    fn idle_game_runs_many_frames() {
+        let mut game = new_game();
+        let frame_time = Duration::from_secs_f32(1.0 / 60.0);
+
+        for _ in 0..5000 {
+            game.step(frame_time);
+        }
+
+        assert!(game.world().read_resource::<Wave>().number >= 1);
+    }
+
+    #[test]
+    fn spinning_and_firing_scores_points() {
+        let mut game = new_game();
+        let frame_time = Duration::from_secs_f32(1.0 / 60.0);
+
+        {
+            let mut input = game.input_mut();
+            input.actions.turn_left = true;
+            input.actions.fire = true;
+        }
+
+        for frame in 0..10_000 {
+            // Thrust now and then, so the ship moves around the screen.
+            game.input_mut().actions.accel_forward = frame % 300 < 30;
+
+            game.step(frame_time);
+
+            if game.state() == GameState::GameOver {
+                break;
+            }
+        }
+
+        assert!(game.world().read_resource::<Score>().points > 0);
+    }
+
+    #[test]
+    fn hyperspace_stops_ship() {
+        // The jump never fails, and there are no asteroids to land on,
+        // so the ship must survive it.
+        let prefabs_text = include_str!("../assets/prefabs.ron");
+        let failure_chance = "hyperspace_failure_chance: 0.1";
+        assert!(prefabs_text.contains(failure_chance));
+        let prefabs: Prefabs =
+            ron::from_str(&prefabs_text.replace(failure_chance, "hyperspace_failure_chance: 0.0"))
+                .unwrap();
+
+        let mut game = game_without_asteroids(42, prefabs);
+        let frame_time = Duration::from_secs_f32(1.0 / 60.0);
+
+        game.input_mut().actions.hyperspace = true;
+        game.step(frame_time);
+        game.input_mut().actions.hyperspace = false;
+        assert_eq!(game.world().read_storage::<HyperspaceJump>().count(), 1);
+
+        for _ in 0..30 {
+            game.step(frame_time);
+        }
+
+        assert_eq!(game.world().read_storage::<HyperspaceJump>().count(), 0);
+        let world = game.world();
+        let velocities: Vec<_> = (
+            &world.read_storage::<Player>(),
+            &world.read_storage::<Physical>(),
+        )
+            .join()
+            .map(|(_, physical)| physical.velocity())
+            .collect();
+        assert_eq!(velocities, vec![Vector2::zeros()]);
+    }
+
+    #[test]
+    fn saucers_aim_better_as_score_rises() {
+        // Large saucers miss by up to 0.5 radians in a new game, but only 0.15 at best.
+        let new_game_errors: Vec<f32> = (0..8).map(|seed| first_shot_error(seed, 0)).collect();
+        let best_errors: Vec<f32> = (0..8).map(|seed| first_shot_error(seed, 40_000)).collect();
+
+        assert!(
+            best_errors.iter().all(|&error| error <= 0.15 + 1e-3),
+            "{:?}",
+            best_errors
+        );
+        assert!(
+            new_game_errors.iter().any(|&error| error > 0.15),
+            "{:?}",
+            new_game_errors
+        );
+    }
+
+    #[test]
+    fn saucer_flies_its_waypoints_then_leaves() {
+        let mut game = game_without_asteroids(0, Prefabs::default());
+
+        // Without a ship, nothing can shoot the saucer down or be shot at.
+        let player = player_entity(&game);
+        game.world.write_resource::<Lives>().remaining = 0;
+        game.world
+            .write_storage::<Destroy>()
+            .insert(player, Destroy)
+            .unwrap();
+
+        let waypoints = vec![Vector2::new(0.0, 0.5), Vector2::new(0.6, -0.5)];
+        let saucer = add_large_saucer(&mut game, Vector2::new(-0.6, -0.5), waypoints.clone());
+        let frame_time = Duration::from_secs_f32(1.0 / 60.0);
+
+        // Counts the waypoints passed through, in order.
+        let mut reached = 0;
+        let mut frames = 0;
+        while game.world().is_alive(saucer) {
+            assert!(frames < 60 * 30, "saucer never left");
+
+            if let (Some(pos), Some(&waypoint)) =
+                (position_of(&game, saucer), waypoints.get(reached))
+            {
+                if (pos - waypoint).norm() < 0.15 {
+                    reached += 1;
+                }
+            }
+
+            game.step(frame_time);
+            frames += 1;
+        }
+
+        assert_eq!(reached, waypoints.len());
+    }
+
+    #[test]
+    fn changing_state_releases_actions() {
+        let mut game = new_game();
+        game.input_mut().actions.fire = true;
+
+        game.set_state(GameState::Paused);
+        game.set_state(GameState::Playing);
+
+        assert!(!game.input_mut().actions.fire);
+    }
+
+    #[test]
+    fn variable_frame_times() {
+        let mut game = new_game();
+
+        for frame in 0..2000 {
+            // Mix of long and very short frames.
+            let millis = if frame % 7 == 0 { 120 } else { 1 + frame % 20 };
+            game.step(Duration::from_millis(millis));
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_game() {
+        let seed = 1234;
+        let new_seeded_game = |physics_clock: PhysicsClock| {
+            Game::new(
+                (4.0 / 3.0, 1.0),
+                GameState::Playing,
+                WaveTable::default(),
+                Prefabs::default(),
+                physics_clock,
+                seed,
+            )
+        };
+
+        // Not the default clock, so playback only matches if it uses the recorded one.
+        let clock = PhysicsClock::new(90.0, 3, RenderBlend::Interpolate);
+        let mut replay = Replay::new(
+            seed,
+            &clock,
+            Prefabs::default().hash(),
+            WaveTable::default().hash(),
+        );
+        let mut game = new_seeded_game(clock);
+        for frame in 0..2000 {
+            {
+                let mut input = game.input_mut();
+                input.actions.turn_left = frame % 200 < 120;
+                input.actions.accel_forward = frame % 300 < 30;
+                input.actions.fire = frame % 3 == 0;
+            }
+            let frame_time = if frame % 5 == 0 { 0.03 } else { 1.0 / 60.0 };
+
+            replay.record(frame_time, game.input_mut().actions);
+            game.step_seconds(frame_time);
+        }
+
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        let mut replayed = new_seeded_game(loaded.clock().clone());
+        for frame in loaded.frames() {
+            replayed.input_mut().actions = frame.actions;
+            replayed.step_seconds(frame.frame_time);
+        }
+
+        assert_eq!(
+            replayed.world().read_resource::<Score>().points,
+            game.world().read_resource::<Score>().points
+        );
+        assert_eq!(positions(&replayed), positions(&game));
+    }
+}
@@ -0,0 +1,20 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameState {
+    Title,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        GameState::Title
+    }
+}
+
+impl GameState {
+    /// Whether the world should be simulated in this state.
+    pub fn is_running(self) -> bool {
+        matches!(self, GameState::Playing | GameState::GameOver)
+    }
+}
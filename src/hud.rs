@@ -0,0 +1,108 @@
+use crate::game_state::GameState;
+use crate::na::Point2;
+use crate::score::HighScores;
+use graphics::color::Color;
+use graphics::font;
+use graphics::screen::ScreenRender;
+
+/// Draws the score, lives and menu text over the game.
+pub struct Hud {
+    max_x: f32,
+    max_y: f32,
+
+    margin: f32,
+    text_size: f32,
+    title_size: f32,
+    color: Color,
+}
+
+impl Hud {
+    pub fn new((max_x, max_y): (f32, f32)) -> Self {
+        Hud {
+            max_x,
+            max_y,
+
+            margin: 0.05,
+            text_size: 0.06,
+            title_size: 0.2,
+            color: Color::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn render(
+        &self,
+        screen_render: &mut ScreenRender,
+        state: GameState,
+        score: u32,
+        lives: u32,
+        high_scores: &HighScores,
+        last_rank: Option<usize>,
+    ) {
+        match state {
+            GameState::Title => {
+                self.draw_centered(screen_render, 0.3, self.title_size, "ASTEROIDS");
+                self.draw_centered(screen_render, -0.1, self.text_size, "PRESS ENTER TO START");
+
+                if let Some(high_score) = high_scores.scores().first() {
+                    let text = format!("HIGH SCORE {}", high_score);
+                    self.draw_centered(screen_render, -0.3, self.text_size, &text);
+                }
+            }
+            GameState::Playing => self.draw_status(screen_render, score, lives),
+            GameState::Paused => {
+                self.draw_status(screen_render, score, lives);
+                self.draw_centered(screen_render, 0.1, 2.0 * self.text_size, "PAUSED");
+                self.draw_centered(
+                    screen_render,
+                    -0.1,
+                    self.text_size,
+                    "P TO RESUME  Q TO QUIT  C FOR CONTROLS",
+                );
+            }
+            GameState::GameOver => {
+                self.draw_status(screen_render, score, lives);
+                self.draw_centered(screen_render, 0.6, 2.0 * self.text_size, "GAME OVER");
+
+                let line_height = 1.5 * self.text_size;
+                let mut y = 0.4;
+                for (i, high_score) in high_scores.scores().iter().enumerate() {
+                    let marker = if Some(i) == last_rank { "<" } else { " " };
+                    let text = format!("{:2}. {:6} {}", i + 1, high_score, marker);
+                    self.draw_centered(screen_render, y, self.text_size, &text);
+                    y -= line_height;
+                }
+            }
+        }
+    }
+
+    /// Draws a line of text below the menu text.
+    pub fn render_prompt(&self, screen_render: &mut ScreenRender, text: &str) {
+        self.draw_centered(screen_render, -0.3, self.text_size, text);
+    }
+
+    fn draw_status(&self, screen_render: &mut ScreenRender, score: u32, lives: u32) {
+        let top = self.max_y - self.margin - self.text_size;
+
+        let score_text = format!("{}", score);
+        screen_render.draw_text(
+            Point2::new(-self.max_x + self.margin, top),
+            self.text_size,
+            self.color,
+            &score_text,
+        );
+
+        let lives_text = format!("SHIPS {}", lives);
+        let lives_width = font::text_width(self.text_size, &lives_text);
+        screen_render.draw_text(
+            Point2::new(self.max_x - self.margin - lives_width, top),
+            self.text_size,
+            self.color,
+            &lives_text,
+        );
+    }
+
+    fn draw_centered(&self, screen_render: &mut ScreenRender, y: f32, size: f32, text: &str) {
+        let width = font::text_width(size, text);
+        screen_render.draw_text(Point2::new(-0.5 * width, y), size, self.color, text);
+    }
+}
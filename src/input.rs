@@ -1,4 +1,43 @@
-#[derive(Default)]
+use serde::{Deserialize, Serialize};
+
+/// Something the player can do, which can be bound to keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    AccelForward,
+    AccelRight,
+    AccelLeft,
+    TurnRight,
+    TurnLeft,
+    Fire,
+    Hyperspace,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::AccelForward,
+        Action::AccelRight,
+        Action::AccelLeft,
+        Action::TurnRight,
+        Action::TurnLeft,
+        Action::Fire,
+        Action::Hyperspace,
+    ];
+
+    /// Name shown to the player.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::AccelForward => "THRUST",
+            Action::AccelRight => "STRAFE RIGHT",
+            Action::AccelLeft => "STRAFE LEFT",
+            Action::TurnRight => "TURN RIGHT",
+            Action::TurnLeft => "TURN LEFT",
+            Action::Fire => "FIRE",
+            Action::Hyperspace => "HYPERSPACE",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Actions {
     pub accel_forward: bool,
     pub accel_right: bool,
@@ -6,6 +45,81 @@ pub struct Actions {
 
     pub turn_right: bool,
     pub turn_left: bool,
+
+    pub fire: bool,
+    pub hyperspace: bool,
+
+    // Proportional controls, such as gamepad sticks. These are combined with
+    // the on/off controls above, and go from -1.0 to 1.0 with positive
+    // values to the right. Thrust only goes forward.
+    pub thrust: f32,
+    pub strafe: f32,
+    pub turn: f32,
+}
+
+impl Actions {
+    pub fn set(&mut self, action: Action, on: bool) {
+        match action {
+            Action::AccelForward => self.accel_forward = on,
+            Action::AccelRight => self.accel_right = on,
+            Action::AccelLeft => self.accel_left = on,
+            Action::TurnRight => self.turn_right = on,
+            Action::TurnLeft => self.turn_left = on,
+            Action::Fire => self.fire = on,
+            Action::Hyperspace => self.hyperspace = on,
+        }
+    }
+
+    /// How hard to thrust forward, from 0.0 to 1.0.
+    pub fn thrust_amount(&self) -> f32 {
+        if self.accel_forward {
+            1.0
+        } else {
+            self.thrust.clamp(0.0, 1.0)
+        }
+    }
+
+    /// How hard to thrust sideways, from -1.0 to 1.0.
+    pub fn strafe_amount(&self) -> f32 {
+        let keys = self.accel_right as i32 - self.accel_left as i32;
+        (keys as f32 + self.strafe).clamp(-1.0, 1.0)
+    }
+
+    /// How fast to turn, from -1.0 to 1.0.
+    pub fn turn_amount(&self) -> f32 {
+        let keys = self.turn_right as i32 - self.turn_left as i32;
+        (keys as f32 + self.turn).clamp(-1.0, 1.0)
+    }
+
+    /// Packs the on/off actions into one bit each.
+    pub fn to_bits(self) -> u8 {
+        [
+            self.accel_forward,
+            self.accel_right,
+            self.accel_left,
+            self.turn_right,
+            self.turn_left,
+            self.fire,
+            self.hyperspace,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, &on)| bits | ((on as u8) << i))
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        let on = |i: u8| bits & (1 << i) != 0;
+        Actions {
+            accel_forward: on(0),
+            accel_right: on(1),
+            accel_left: on(2),
+            turn_right: on(3),
+            turn_left: on(4),
+            fire: on(5),
+            hyperspace: on(6),
+            ..Actions::default()
+        }
+    }
 }
 
 #[derive(Default)]
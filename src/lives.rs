@@ -0,0 +1,175 @@
+use specs::storage::BTreeStorage;
+use specs::{
+    Builder, Component, Entities, Join, LazyUpdate, Read, ReadStorage, System, Write, WriteStorage,
+};
+
+use crate::na;
+use crate::na::{Isometry2, Vector2};
+
+use crate::asteroid::Asteroid;
+use crate::game_state::GameState;
+use crate::input::Input;
+use crate::physics::{Destroy, Physical, RecheckContacts};
+use crate::player::Player;
+use crate::prefab::{Overrides, Prefabs};
+use crate::shape::Shape;
+
+/// Number of ships left in reserve, not counting the one in play.
+pub struct Lives {
+    pub remaining: u32,
+}
+
+impl Default for Lives {
+    fn default() -> Self {
+        Lives { remaining: 2 }
+    }
+}
+
+/// Ships with this component can not be destroyed by asteroids.
+#[derive(Component, Debug)]
+#[storage(BTreeStorage)]
+pub struct Invulnerable {
+    remaining: f32,
+}
+
+impl Invulnerable {
+    pub fn new(duration: f32) -> Self {
+        Invulnerable {
+            remaining: duration,
+        }
+    }
+
+    /// Whether the ship should be drawn dimmed this frame.
+    pub fn blink_off(&self) -> bool {
+        let blink_period = 0.15;
+        (self.remaining / blink_period) as i32 % 2 == 1
+    }
+}
+
+pub struct InvulnerabilityTimer;
+
+impl<'a> System<'a> for InvulnerabilityTimer {
+    type SystemData = (
+        Read<'a, Input>,
+        Entities<'a>,
+        WriteStorage<'a, Invulnerable>,
+        WriteStorage<'a, RecheckContacts>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (input, entities, mut invulnerables, mut rechecks) = data;
+
+        let mut expired = Vec::new();
+        for (e, invulnerable) in (&entities, &mut invulnerables).join() {
+            invulnerable.remaining -= input.frame_time;
+
+            if invulnerable.remaining <= 0.0 {
+                expired.push(e);
+            }
+        }
+
+        // The ship may be sitting inside an asteroid already, which would
+        // otherwise never be reported.
+        for e in expired {
+            invulnerables.remove(e);
+            rechecks
+                .insert(e, RecheckContacts)
+                .expect("failed to recheck contacts");
+        }
+    }
+}
+
+/// Brings the ship back after it has been destroyed, once the centre of
+/// the screen is clear of asteroids. Ends the game when no lives are left.
+pub struct Respawn {
+    respawn_delay: f32,
+    // No asteroid may be within this distance of the origin for the ship to respawn.
+    clear_radius: f32,
+    invulnerable_time: f32,
+
+    // Time until the ship may respawn, set while waiting.
+    respawn_timer: Option<f32>,
+}
+
+impl Respawn {
+    pub fn new() -> Self {
+        Respawn {
+            respawn_delay: 2.0,
+            clear_radius: 0.3,
+            invulnerable_time: 3.0,
+
+            respawn_timer: None,
+        }
+    }
+}
+
+impl<'a> System<'a> for Respawn {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, Input>,
+        Read<'a, LazyUpdate>,
+        Read<'a, Prefabs>,
+        Write<'a, Lives>,
+        Write<'a, GameState>,
+        Entities<'a>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Asteroid>,
+        ReadStorage<'a, Shape>,
+        ReadStorage<'a, Physical>,
+        ReadStorage<'a, Destroy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            input,
+            lazy,
+            prefabs,
+            mut lives,
+            mut game_state,
+            entities,
+            players,
+            asteroids,
+            shapes,
+            physicals,
+            destroys,
+        ) = data;
+
+        if (&players, !&destroys).join().next().is_some() {
+            self.respawn_timer = None;
+            return;
+        }
+
+        if lives.remaining == 0 {
+            if *game_state == GameState::Playing {
+                *game_state = GameState::GameOver;
+            }
+            return;
+        }
+
+        let respawn_timer = self.respawn_timer.get_or_insert(self.respawn_delay);
+        *respawn_timer -= input.frame_time;
+        if *respawn_timer > 0.0 {
+            return;
+        }
+
+        let clear_radius = self.clear_radius;
+        let centre_clear = (&asteroids, &shapes, &physicals)
+            .join()
+            .all(|(_, shape, physical)| {
+                physical.position().translation.vector.norm() > clear_radius + shape.radius
+            });
+
+        if centre_clear {
+            lives.remaining -= 1;
+            self.respawn_timer = None;
+
+            let player_pos = Isometry2::new(Vector2::new(0.0, 0.0), na::zero());
+            prefabs
+                .get("player")
+                .expect("player prefab is missing")
+                .apply(lazy.create_entity(&entities), Overrides::at(player_pos))
+                .with(Invulnerable::new(self.invulnerable_time))
+                .build();
+        }
+    }
+}
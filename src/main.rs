@@ -1,140 +1,480 @@
 #[macro_use]
 extern crate specs_derive;
 
+mod assets;
+mod asteroid;
+mod bindings;
+mod bullet;
+mod clock;
+mod color;
+mod game;
+mod game_state;
+mod hud;
 mod input;
+mod lives;
+mod particles;
 mod physics;
 mod player;
+mod prefab;
+mod renderable;
 mod renderer;
+mod replay;
+mod rng;
+mod saucer;
+mod score;
+mod screenshot;
 mod shape;
+mod sound;
+mod state_keys;
+mod waves;
 
-use crate::input::Input;
-use crate::physics::{AddCollision, CollisionCreator, Physics};
-use crate::player::{Player, PlayerController};
-use crate::renderer::{Renderable, Renderer};
-use crate::shape::Shape;
-use anyhow::Result;
+use crate::bindings::{Controls, KeyBindings, RESERVED_KEYS};
+use crate::clock::PhysicsClock;
+use crate::game::Game;
+use crate::game_state::GameState;
+use crate::hud::Hud;
+use crate::input::{Action, Actions, Input};
+use crate::lives::Lives;
+use crate::particles::Particles;
+use crate::player::Player;
+use crate::prefab::Prefabs;
+use crate::renderer::{Renderer, ShapeStyle};
+use crate::replay::{Replay, ReplayPlayer};
+use crate::score::{HighScores, Score};
+use crate::sound::{SoundBank, SoundQueue};
+use crate::state_keys::StateChange;
+use crate::waves::WaveTable;
+use anyhow::{bail, ensure, Context, Result};
+use audio::Audio;
 use graphics::{
     color::Color,
+    errors::RenderError,
     events::{Event, Key},
+    image::Image,
     screen::{Screen, ScreenCallbacks, ScreenRender, ScreenRunner},
 };
 use nalgebra as na;
-use nalgebra::{Isometry2, Vector2};
-use specs::{Builder, Dispatcher, DispatcherBuilder, World, WorldExt};
+use specs::{Join, WorldExt};
+use std::path::PathBuf;
 use std::time::Duration;
 
 fn main() -> Result<()> {
+    let args = Args::parse()?;
+
     let width = 800.0;
     let height = 600.0;
     let clear_color = Color::new(0.2, 0.2, 0.5, 1.0);
     let runner = ScreenRunner::create(width, height, "Bouncing Balls", clear_color)?;
 
-    let app = App::new(width, height);
+    let high_scores = HighScores::load().unwrap_or_else(|err| {
+        eprintln!("{:#}", err);
+        HighScores::default()
+    });
+
+    let wave_table = WaveTable::load().unwrap_or_else(|err| {
+        eprintln!("{:#}", err);
+        WaveTable::default()
+    });
+
+    let prefabs = Prefabs::load().unwrap_or_else(|err| {
+        eprintln!("{:#}", err);
+        Prefabs::default()
+    });
+
+    if let Some(replay) = &args.replay {
+        ensure!(
+            replay.prefab_hash() == prefabs.hash(),
+            "The replay was recorded with different prefabs, so it would not play back the same"
+        );
+        ensure!(
+            replay.wave_hash() == wave_table.hash(),
+            "The replay was recorded with different waves, so it would not play back the same"
+        );
+    }
+
+    let physics_clock = PhysicsClock::load().unwrap_or_else(|err| {
+        eprintln!("{:#}", err);
+        PhysicsClock::default()
+    });
+
+    let bindings = KeyBindings::load().unwrap_or_else(|err| {
+        eprintln!("{:#}", err);
+        KeyBindings::default()
+    });
+
+    let sounds = SoundBank::load(Audio::new());
+
+    let app = App::new(
+        width,
+        height,
+        high_scores,
+        wave_table,
+        prefabs,
+        physics_clock,
+        Controls::new(bindings),
+        sounds,
+        args,
+    );
 
     runner.run(app);
 }
 
+/// Command line options.
+struct Args {
+    // Each game is recorded to this file, replacing the previous one.
+    record_path: Option<PathBuf>,
+    // Played back instead of taking input from the player.
+    replay: Option<Replay>,
+    shape_style: ShapeStyle,
+}
+
+impl Args {
+    fn parse() -> Result<Self> {
+        let mut args = Args {
+            record_path: None,
+            replay: None,
+            shape_style: ShapeStyle::Outline { thickness: 0.004 },
+        };
+
+        let mut arg_iter = std::env::args().skip(1);
+        while let Some(arg) = arg_iter.next() {
+            match arg.as_str() {
+                "--record" => {
+                    let path = arg_iter.next().context("--record needs a file name")?;
+                    args.record_path = Some(PathBuf::from(path));
+                }
+                "--replay" => {
+                    let path = arg_iter.next().context("--replay needs a file name")?;
+                    args.replay = Some(Replay::load(&PathBuf::from(path))?);
+                }
+                "--filled" => args.shape_style = ShapeStyle::Filled,
+                _ => bail!("Unknown argument {}", arg),
+            }
+        }
+
+        Ok(args)
+    }
+}
+
 struct App<'a, 'b> {
-    world: World,
-    dispatcher: Box<Dispatcher<'a, 'b>>,
+    game: Game<'a, 'b>,
     renderer: Renderer,
+    hud: Hud,
+
+    wave_table: WaveTable,
+    prefabs: Prefabs,
+    physics_clock: PhysicsClock,
+
+    controls: Controls,
+    // Index into Action::ALL of the action waiting for a new key.
+    rebinding: Option<usize>,
+
+    sounds: SoundBank,
+
+    high_scores: HighScores,
+    // Position of the last game's score in high_scores, if it made the table.
+    last_rank: Option<usize>,
+
+    record_path: Option<PathBuf>,
+    // Input of the current game, if it is being recorded.
+    recording: Option<Replay>,
+    // Set when the current game is a replay.
+    playback: Option<ReplayPlayer>,
 }
 
-impl App<'_, '_> {
-    fn new(width: f64, height: f64) -> Self {
-        let renderer = Renderer::new(width, height);
-
-        let mut rng = rand::thread_rng();
-
-        let mut world = World::new();
-        // Renderable is not used in a dispatched system, so
-        // we must explicitly register it.
-        world.register::<Renderable>();
-        let mut dispatcher = Box::new(
-            DispatcherBuilder::new()
-                .with(PlayerController, "player", &[])
-                .with(CollisionCreator, "collision_creator", &[])
-                .with(
-                    Physics::new(renderer.get_max_coords()),
-                    "physics",
-                    &["player", "collision_creator"],
-                )
-                .build(),
+impl<'a, 'b> App<'a, 'b> {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        width: f64,
+        height: f64,
+        high_scores: HighScores,
+        wave_table: WaveTable,
+        prefabs: Prefabs,
+        physics_clock: PhysicsClock,
+        controls: Controls,
+        sounds: SoundBank,
+        args: Args,
+    ) -> Self {
+        let renderer = Renderer::new(width, height, args.shape_style);
+        let hud = Hud::new(renderer.get_max_coords());
+        let game = Game::new(
+            renderer.get_max_coords(),
+            GameState::Title,
+            wave_table.clone(),
+            prefabs.clone(),
+            physics_clock.clone(),
+            rand::random(),
         );
-        dispatcher.setup(&mut world);
-
-        let player_pos = Isometry2::new(Vector2::new(0.0, 0.0), na::zero());
-        let player_shape = Shape::create_ship();
-        let player_physical = AddCollision::new(player_pos, Vector2::new(0.25, 0.5));
-        world
-            .create_entity()
-            .with(Player::new())
-            .with(player_shape)
-            .with(player_physical)
-            .with(Renderable::new(Color::new(1.0, 1.0, 1.0, 1.0)))
-            .build();
-
-        let astroid_shape = Shape::create_asteroid(&mut rng);
-        let asteroid_pos = Isometry2::new(Vector2::new(0.5, 0.5), na::zero());
-        let astroid_physical = AddCollision::new(asteroid_pos, Vector2::new(0.25, 0.5));
-        world
-            .create_entity()
-            .with(astroid_shape)
-            .with(astroid_physical)
-            .with(Renderable::new(Color::new(1.0, 1.0, 1.0, 1.0)))
-            .build();
-
-        let astroid_shape2 = Shape::create_asteroid(&mut rng);
-        let asteroid_pos2 = Isometry2::new(Vector2::new(-0.5, 0.5), na::zero());
-        let astroid_physical2 = AddCollision::new(asteroid_pos2, Vector2::new(-0.25, 0.5));
-        world
-            .create_entity()
-            .with(astroid_shape2)
-            .with(astroid_physical2)
-            .with(Renderable::new(Color::new(1.0, 1.0, 1.0, 1.0)))
-            .build();
-
-        Self {
-            world,
-            dispatcher,
+
+        let mut app = Self {
+            game,
             renderer,
+            hud,
+
+            wave_table,
+            prefabs,
+            physics_clock,
+
+            controls,
+            rebinding: None,
+
+            sounds,
+
+            high_scores,
+            last_rank: None,
+
+            record_path: args.record_path,
+            recording: None,
+            playback: None,
+        };
+
+        if let Some(replay) = args.replay {
+            let playback = ReplayPlayer::new(replay);
+            app.start_game(playback.seed(), Some(playback));
+        }
+
+        app
+    }
+
+    /// Throws away the current world and starts playing a fresh one.
+    fn new_game(&mut self) {
+        self.start_game(rand::random(), None);
+    }
+
+    fn start_game(&mut self, seed: u64, playback: Option<ReplayPlayer>) {
+        // Replays step with the clock they were recorded with.
+        let physics_clock = match &playback {
+            Some(playback) => playback.clock().clone(),
+            None => self.physics_clock.clone(),
+        };
+
+        self.game = Game::new(
+            self.renderer.get_max_coords(),
+            GameState::Playing,
+            self.wave_table.clone(),
+            self.prefabs.clone(),
+            physics_clock,
+            seed,
+        );
+        self.renderer.clear_shapes();
+        self.last_rank = None;
+        self.game.input_mut().actions = self.controls.actions();
+
+        // Replays are not recorded again.
+        self.recording = match playback {
+            Some(_) => None,
+            None => self.record_path.as_ref().map(|_| {
+                Replay::new(
+                    seed,
+                    &self.physics_clock,
+                    self.prefabs.hash(),
+                    self.wave_table.hash(),
+                )
+            }),
+        };
+        self.playback = playback;
+    }
+
+    /// Returns the frame time for the next step.
+    /// Input comes from the replay when playing one back, and is recorded when recording.
+    fn next_frame_time(&mut self, frame_delta: Duration) -> f32 {
+        let mut frame_time = frame_delta.as_secs_f32();
+
+        if let Some(playback) = &mut self.playback {
+            let mut input = self.game.input_mut();
+            match playback.next_frame() {
+                Some(frame) => {
+                    frame_time = frame.frame_time;
+                    input.actions = frame.actions;
+                }
+                None => input.actions = Actions::default(),
+            }
+        }
+
+        if let Some(recording) = &mut self.recording {
+            recording.record(frame_time, self.game.input_mut().actions);
+        }
+
+        frame_time
+    }
+
+    /// Plays the sounds queued by the last step, and keeps the thrust loop in
+    /// step with the ship.
+    fn update_sounds(&mut self) {
+        let world = self.game.world();
+        for event in world.write_resource::<SoundQueue>().drain() {
+            self.sounds.play(event);
+        }
+
+        let has_player = world.read_storage::<Player>().join().next().is_some();
+        let thrust = if self.game.state() == GameState::Playing && has_player {
+            world.read_resource::<Input>().actions.thrust_amount()
+        } else {
+            0.0
+        };
+        self.sounds.set_thrust(thrust);
+    }
+
+    fn save_recording(&mut self) {
+        if let (Some(path), Some(recording)) = (&self.record_path, self.recording.take()) {
+            if let Err(err) = recording.save(path) {
+                eprintln!("{:#}", err);
+            }
+        }
+    }
+
+    /// Binds key to the action being rebound, and moves on to the next one.
+    /// Escape stops early, keeping the keys bound so far.
+    /// Keys the app itself uses are ignored, and the action keeps waiting.
+    fn rebind_key(&mut self, key: Key) {
+        let index = match self.rebinding {
+            Some(index) => index,
+            None => return,
+        };
+
+        if RESERVED_KEYS.contains(&key) {
+            return;
+        }
+
+        if key != Key::Escape {
+            self.controls.rebind(Action::ALL[index], key);
+        }
+
+        if key != Key::Escape && index + 1 < Action::ALL.len() {
+            self.rebinding = Some(index + 1);
+        } else {
+            self.rebinding = None;
+            if let Err(err) = self.controls.bindings().save() {
+                eprintln!("{:#}", err);
+            }
+        }
+    }
+
+    fn record_high_score(&mut self) {
+        let score = self.game.world().read_resource::<Score>().points;
+        self.last_rank = self.high_scores.add(score);
+
+        if self.last_rank.is_some() {
+            if let Err(err) = self.high_scores.save() {
+                eprintln!("{:#}", err);
+            }
+        }
+    }
+
+    /// Applies any game state change caused by key.
+    /// Returns true if the key was consumed.
+    fn handle_state_key(&mut self, screen: &mut Screen, key: &Key) -> bool {
+        match state_keys::handle_key(self.game.state(), key) {
+            Some(StateChange::Switch(new_state)) => {
+                if new_state == GameState::Title {
+                    self.save_recording();
+                }
+                self.game.set_state(new_state);
+                true
+            }
+            Some(StateChange::NewGame) => {
+                self.new_game();
+                true
+            }
+            Some(StateChange::Exit) => {
+                screen.exit();
+                true
+            }
+            None => false,
         }
     }
 }
 
 impl ScreenCallbacks for App<'_, '_> {
-    fn handle_event(&mut self, _screen: &mut Screen, event: Event) {
-        let mut input = self.world.write_resource::<Input>();
-        match event {
-            Event::KeyPress { key: Key::W, down } => input.actions.accel_forward = down,
-            Event::KeyPress { key: Key::D, down } => input.actions.accel_right = down,
-            Event::KeyPress { key: Key::A, down } => input.actions.accel_left = down,
-
-            Event::KeyPress {
-                key: Key::Right,
-                down,
-            } => input.actions.turn_right = down,
-            Event::KeyPress {
-                key: Key::Left,
-                down,
-            } => input.actions.turn_left = down,
-
-            _ => (),
+    fn handle_event(&mut self, screen: &mut Screen, event: Event) {
+        if let Event::KeyPress { key, down } = event {
+            if self.rebinding.is_some() {
+                if down {
+                    self.rebind_key(key);
+                } else {
+                    // Keys held when rebinding started must still be released.
+                    self.controls.key_changed(key, down);
+                    self.game.input_mut().actions = self.controls.actions();
+                }
+                return;
+            }
+
+            if down {
+                if self.handle_state_key(screen, &key) {
+                    return;
+                }
+
+                if key == Key::F12 {
+                    screen.capture_frame();
+                    return;
+                }
+
+                if key == Key::C && self.game.state() == GameState::Paused {
+                    self.rebinding = Some(0);
+                    return;
+                }
+            }
+
+            self.controls.key_changed(key, down);
+            self.game.input_mut().actions = self.controls.actions();
+        } else if self.controls.gamepad_event(&event) {
+            self.game.input_mut().actions = self.controls.actions();
         }
     }
     fn update(&mut self, screen: &mut Screen, frame_delta: Duration) {
-        {
-            let mut input = self.world.write_resource::<Input>();
-            input.frame_time = frame_delta.as_secs_f32();
+        let state = self.game.state();
+        if state.is_running() {
+            let frame_time = self.next_frame_time(frame_delta);
+            self.game.step_seconds(frame_time);
+
+            if state == GameState::Playing && self.game.state() == GameState::GameOver {
+                self.save_recording();
+
+                // Replayed scores were already recorded when they were played.
+                if self.playback.is_none() {
+                    self.record_high_score();
+                }
+            }
         }
-        self.dispatcher.dispatch(&self.world);
-        self.world.maintain();
 
-        self.renderer.update(screen, self.world.system_data());
+        self.update_sounds();
+
+        self.renderer
+            .update(screen, self.game.world().system_data());
     }
 
-    fn render(&self, screen_render: ScreenRender) {
+    fn render(&self, mut screen_render: ScreenRender) {
+        let world = self.game.world();
+        self.renderer
+            .render_particles(&mut screen_render, &world.read_resource::<Particles>());
         self.renderer
-            .render(screen_render, self.world.system_data())
+            .render(&mut screen_render, world.system_data());
+
+        let state = self.game.state();
+        let score = world.read_resource::<Score>().points;
+        let lives = world.read_resource::<Lives>().remaining;
+        self.hud.render(
+            &mut screen_render,
+            state,
+            score,
+            lives,
+            &self.high_scores,
+            self.last_rank,
+        );
+
+        if let Some(index) = self.rebinding {
+            let prompt = format!("PRESS KEY FOR {}", Action::ALL[index].name());
+            self.hud.render_prompt(&mut screen_render, &prompt);
+        }
+    }
+
+    fn frame_captured(&mut self, _screen: &mut Screen, image: Result<Image, RenderError>) {
+        let saved = image
+            .context("Failed to capture the frame")
+            .and_then(|image| screenshot::save(&image));
+        match saved {
+            Ok(path) => eprintln!("Saved screenshot to {}", path.display()),
+            Err(err) => eprintln!("{:#}", err),
+        }
     }
 }
@@ -0,0 +1,261 @@
+use std::f32;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use specs::storage::BTreeStorage;
+use specs::{Component, Join, Read, ReadStorage, System, Write, WriteStorage};
+
+use crate::na::{Point2, Rotation2, Vector2};
+
+use crate::color::Color;
+use crate::input::Input;
+use crate::physics::Physical;
+use serde::{Deserialize, Serialize};
+
+/// How a group of particles looks and moves.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ParticleParams {
+    pub speed: f32,
+    // Each particle's speed is randomly scaled by up to this fraction.
+    pub speed_variation: f32,
+    // Total angle that the particles are spread across.
+    pub spread: f32,
+    pub lifetime: f32,
+    pub size: f32,
+    pub color: Color,
+}
+
+impl ParticleParams {
+    pub fn explosion() -> Self {
+        ParticleParams {
+            speed: 0.3,
+            speed_variation: 0.8,
+            spread: 2.0 * f32::consts::PI,
+            lifetime: 0.8,
+            size: 0.004,
+            color: Color::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Particle {
+    pos: Vector2<f32>,
+    vel: Vector2<f32>,
+    age: f32,
+    lifetime: f32,
+    size: f32,
+    color: Color,
+}
+
+impl Particle {
+    pub fn position(&self) -> Point2<f32> {
+        Point2::from(self.pos)
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Fades out over the particle's lifetime.
+    pub fn color(&self) -> Color {
+        let fade = 1.0 - self.age / self.lifetime;
+        Color::new(
+            self.color.r,
+            self.color.g,
+            self.color.b,
+            self.color.a * fade,
+        )
+    }
+}
+
+/// Every live particle. Particles are not entities, so that there can be
+/// thousands of them without burdening the world.
+pub struct Particles {
+    particles: Vec<Particle>,
+    max_particles: usize,
+
+    // Kept apart from GameRng, so that effects do not change the game.
+    rng: StdRng,
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Particles {
+            particles: Vec::new(),
+            max_particles: 5000,
+
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+}
+
+impl Particles {
+    pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.particles.iter()
+    }
+
+    /// Emits count particles from pos, spread around direction.
+    /// Particles also carry the velocity of whatever emitted them.
+    pub fn emit(
+        &mut self,
+        params: &ParticleParams,
+        count: u32,
+        pos: Vector2<f32>,
+        vel: Vector2<f32>,
+        direction: Vector2<f32>,
+    ) {
+        let available = self.max_particles - self.particles.len();
+        for _ in 0..(count as usize).min(available) {
+            let angle = self.rng.gen_range(-0.5..0.5) * params.spread;
+            let speed =
+                params.speed * (1.0 + params.speed_variation * self.rng.gen_range(-1.0..1.0));
+
+            self.particles.push(Particle {
+                pos,
+                vel: vel + speed * (Rotation2::new(angle) * direction),
+                age: 0.0,
+                lifetime: params.lifetime * self.rng.gen_range(0.5..1.0),
+                size: params.size,
+                color: params.color,
+            });
+        }
+    }
+
+    /// Emits count particles from pos in every direction.
+    pub fn burst(&mut self, params: &ParticleParams, count: u32, pos: Vector2<f32>) {
+        self.emit(params, count, pos, Vector2::zeros(), Vector2::x());
+    }
+
+    /// Moves and ages every particle, removing those that have expired.
+    /// Particles wrap around the screen like everything else.
+    pub fn update(&mut self, frame_time: f32, max_x: f32, max_y: f32) {
+        for particle in &mut self.particles {
+            particle.age += frame_time;
+            particle.pos += frame_time * particle.vel;
+
+            if particle.pos.x > max_x {
+                particle.pos.x -= 2.0 * max_x;
+            } else if particle.pos.x < -max_x {
+                particle.pos.x += 2.0 * max_x;
+            }
+
+            if particle.pos.y > max_y {
+                particle.pos.y -= 2.0 * max_y;
+            } else if particle.pos.y < -max_y {
+                particle.pos.y += 2.0 * max_y;
+            }
+        }
+
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+    }
+}
+
+/// Continuously emits particles from an entity, such as a ship's exhaust.
+#[derive(Component, Debug)]
+#[storage(BTreeStorage)]
+pub struct ParticleEmitter {
+    params: ParticleParams,
+    // Position and direction of the emitter, relative to the entity.
+    offset: Vector2<f32>,
+    direction: Vector2<f32>,
+    // Particles per second at full intensity.
+    rate: f32,
+
+    intensity: f32,
+    // Particles owed from previous frames.
+    accumulator: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(
+        params: ParticleParams,
+        offset: Vector2<f32>,
+        direction: Vector2<f32>,
+        rate: f32,
+    ) -> Self {
+        ParticleEmitter {
+            params,
+            offset,
+            direction,
+            rate,
+
+            intensity: 0.0,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Scales the emission rate, from zero (off) to one (full rate).
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity;
+    }
+}
+
+pub struct ParticleSystem {
+    max_x: f32,
+    max_y: f32,
+}
+
+impl ParticleSystem {
+    pub fn new((max_x, max_y): (f32, f32)) -> Self {
+        ParticleSystem { max_x, max_y }
+    }
+}
+
+impl<'a> System<'a> for ParticleSystem {
+    type SystemData = (
+        Read<'a, Input>,
+        Write<'a, Particles>,
+        WriteStorage<'a, ParticleEmitter>,
+        ReadStorage<'a, Physical>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (input, mut particles, mut emitters, physicals) = data;
+
+        particles.update(input.frame_time, self.max_x, self.max_y);
+
+        for (emitter, physical) in (&mut emitters, &physicals).join() {
+            if emitter.intensity <= 0.0 {
+                emitter.accumulator = 0.0;
+                continue;
+            }
+
+            emitter.accumulator += emitter.intensity * emitter.rate * input.frame_time;
+            let count = emitter.accumulator.floor();
+            emitter.accumulator -= count;
+
+            let pos = physical.render_position();
+            particles.emit(
+                &emitter.params,
+                count as u32,
+                (pos * Point2::from(emitter.offset)).coords,
+                physical.velocity(),
+                pos.rotation * emitter.direction,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParticleParams, Particles};
+    use crate::na::Vector2;
+
+    #[test]
+    fn particles_fade_and_expire() {
+        let mut particles = Particles::default();
+        let params = ParticleParams::explosion();
+        particles.burst(&params, 100, Vector2::zeros());
+        assert_eq!(particles.iter().count(), 100);
+
+        particles.update(0.5 * params.lifetime, 1.0, 1.0);
+        for particle in particles.iter() {
+            assert!(particle.color().a < params.color.a);
+        }
+
+        particles.update(params.lifetime, 1.0, 1.0);
+        assert_eq!(particles.iter().count(), 0);
+    }
+}
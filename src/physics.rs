@@ -1,32 +1,47 @@
+use crate::clock::PhysicsClock;
 use crate::input::Input;
 use crate::shape::Shape;
-use nalgebra::{Isometry2, UnitComplex, Vector2};
-use ncollide2d::shape::{Polyline, ShapeHandle};
+use nalgebra::{Isometry2, Translation2, UnitComplex, Vector2};
+use ncollide2d::pipeline::narrow_phase::ContactEvent;
+use ncollide2d::query::Proximity;
+use ncollide2d::shape::{Compound, ConvexPolygon, ShapeHandle};
 use nphysics2d::{
     algebra::Velocity2,
     force_generator::DefaultForceGeneratorSet,
     joint::DefaultJointConstraintSet,
     object::{
-        BodyPartHandle, ColliderDesc, DefaultBodyHandle, DefaultBodySet, DefaultColliderSet,
-        RigidBodyDesc,
+        BodyPartHandle, ColliderDesc, DefaultBodyHandle, DefaultBodySet, DefaultColliderHandle,
+        DefaultColliderSet, Ground, RigidBodyDesc,
     },
     world::{DefaultGeometricalWorld, DefaultMechanicalWorld},
 };
+use specs::shrev::EventChannel;
 use specs::{
-    Component, Entities, HashMapStorage, Join, LazyUpdate, Read, ReadStorage, System, VecStorage,
-    Write, WriteStorage,
+    Component, Entities, Entity, HashMapStorage, Join, LazyUpdate, NullStorage, Read, ReadStorage,
+    System, VecStorage, Write, WriteStorage,
 };
+use std::collections::HashMap;
 
 #[derive(Component)]
 #[storage(HashMapStorage)]
 pub struct AddCollision {
     pos: Isometry2<f32>,
     vel: Vector2<f32>,
+    spin: f32,
 }
 
 impl AddCollision {
     pub fn new(pos: Isometry2<f32>, vel: Vector2<f32>) -> Self {
-        AddCollision { pos, vel }
+        AddCollision {
+            pos,
+            vel,
+            spin: 0.0,
+        }
+    }
+
+    /// Sets the angular velocity, in radians per second.
+    pub fn with_spin(self, spin: f32) -> Self {
+        AddCollision { spin, ..self }
     }
 }
 
@@ -35,27 +50,44 @@ impl AddCollision {
 pub struct Physical {
     pos: Isometry2<f32>,
     vel: Vector2<f32>,
+    spin: f32,
 
+    // Position before the last physics step.
+    prev_pos: Isometry2<f32>,
     render_pos: Isometry2<f32>,
 
     pulse_accel: Vector2<f32>,
     pulse_rot: f32,
 
     body_handle: DefaultBodyHandle,
+    collider_handle: DefaultColliderHandle,
+    // Copies of the collider across the wraparound edges. See update_ghosts.
+    ghost_handles: [DefaultColliderHandle; 3],
 }
 
 impl Physical {
-    fn new(pos: Isometry2<f32>, vel: Vector2<f32>, body_handle: DefaultBodyHandle) -> Self {
+    fn new(
+        pos: Isometry2<f32>,
+        vel: Vector2<f32>,
+        spin: f32,
+        body_handle: DefaultBodyHandle,
+        collider_handle: DefaultColliderHandle,
+        ghost_handles: [DefaultColliderHandle; 3],
+    ) -> Self {
         Physical {
             pos,
             vel,
+            spin,
 
+            prev_pos: pos,
             render_pos: pos,
 
             pulse_accel: Vector2::new(0.0, 0.0),
             pulse_rot: 0.0,
 
             body_handle,
+            collider_handle,
+            ghost_handles,
         }
     }
 
@@ -63,14 +95,42 @@ impl Physical {
         self.render_pos
     }
 
+    pub fn position(&self) -> Isometry2<f32> {
+        self.pos
+    }
+
+    pub fn velocity(&self) -> Vector2<f32> {
+        self.vel
+    }
+
     pub fn add_relative_pulse(&mut self, accel: Vector2<f32>) {
         self.pulse_accel += self.pos.rotation * accel;
     }
 
+    /// Like add_relative_pulse, but in world space rather than relative to the body's facing.
+    pub fn add_pulse(&mut self, accel: Vector2<f32>) {
+        self.pulse_accel += accel;
+    }
+
     pub fn add_angular_pulse(&mut self, angle: f32) {
         self.pulse_rot += angle;
     }
 
+    /// Moves the body straight to pos and stops it.
+    /// The render position jumps too, rather than sliding across the screen.
+    pub fn teleport(&mut self, bodies: &mut Bodies, pos: Isometry2<f32>) {
+        if let Some(ref mut rigid_body) = bodies.0.rigid_body_mut(self.body_handle) {
+            rigid_body.set_position(pos);
+            rigid_body.set_velocity(Velocity2::zero());
+        }
+
+        self.pos = pos;
+        self.prev_pos = pos;
+        self.render_pos = pos;
+        self.vel = Vector2::zeros();
+        self.pulse_accel = Vector2::zeros();
+    }
+
     fn apply_dynamics(
         &mut self,
         bodies: &mut DefaultBodySet<f32>,
@@ -92,12 +152,19 @@ impl Physical {
                 rigid_body.set_linear_velocity(self.vel);
             }
         }
+    }
+
+    /// Turns the body by one step's worth of rotation.
+    /// This is done before every step, rather than once per frame,
+    /// so that each step's rotation can be blended when rendering.
+    fn apply_rotation(&mut self, bodies: &mut DefaultBodySet<f32>, step_time: f32) {
+        let angular_velocity = self.spin - self.pulse_rot;
 
-        if self.pulse_rot != 0.0 {
+        if angular_velocity != 0.0 {
             if let Some(ref mut rigid_body) = bodies.rigid_body_mut(self.body_handle) {
                 let mut position = *rigid_body.position();
 
-                let delta_angle = UnitComplex::new(-self.pulse_rot * frame_time);
+                let delta_angle = UnitComplex::new(angular_velocity * step_time);
                 position.rotation *= delta_angle;
 
                 rigid_body.set_position(position);
@@ -105,18 +172,92 @@ impl Physical {
         }
     }
 
-    fn apply_step(&mut self, bodies: &DefaultBodySet<f32>, extra_frame_time: f32) {
+    fn store_previous(&mut self, bodies: &DefaultBodySet<f32>) {
+        if let Some(ref rigid_body) = bodies.rigid_body(self.body_handle) {
+            self.prev_pos = *rigid_body.position();
+        }
+    }
+
+    fn apply_step(&mut self, bodies: &DefaultBodySet<f32>) {
         if let Some(ref rigid_body) = bodies.rigid_body(self.body_handle) {
             self.pos = *rigid_body.position();
             self.vel = rigid_body.velocity().linear;
-
-            self.render_pos = self.pos;
-            self.render_pos.translation.vector += self.vel * extra_frame_time;
         }
         self.pulse_accel = Vector2::new(0.0, 0.0);
         self.pulse_rot = 0.0;
     }
 
+    /// Blends the previous and current positions by blend_factor, where 0 is
+    /// the previous position and 1 is the current one.
+    fn update_render_position(&mut self, blend_factor: f32, max_x: f32, max_y: f32) {
+        // A body that crossed an edge jumped to the other side of the
+        // screen, so blend the short way around instead.
+        let mut delta = self.pos.translation.vector - self.prev_pos.translation.vector;
+        if delta.x > max_x {
+            delta.x -= 2.0 * max_x;
+        } else if delta.x < -max_x {
+            delta.x += 2.0 * max_x;
+        }
+        if delta.y > max_y {
+            delta.y -= 2.0 * max_y;
+        } else if delta.y < -max_y {
+            delta.y += 2.0 * max_y;
+        }
+
+        let delta_angle = self
+            .prev_pos
+            .rotation
+            .rotation_to(&self.pos.rotation)
+            .angle();
+
+        // Measured back from the current position, so it stays exact at 1.
+        let t = blend_factor - 1.0;
+        self.render_pos = Isometry2::from_parts(
+            Translation2::from(self.pos.translation.vector + t * delta),
+            self.pos.rotation * UnitComplex::new(t * delta_angle),
+        );
+    }
+
+    /// Moves the ghost colliders to the body's position, shifted across the
+    /// nearest vertical edge, the nearest horizontal edge, and both.
+    /// A body near an edge is drawn on the far side too, and its ghosts let
+    /// it be hit there. Ghosts are placed before each step, so they trail the
+    /// body by up to one step.
+    fn update_ghosts(
+        &self,
+        bodies: &DefaultBodySet<f32>,
+        colliders: &mut DefaultColliderSet<f32>,
+        max_x: f32,
+        max_y: f32,
+    ) {
+        let pos = match bodies.rigid_body(self.body_handle) {
+            Some(rigid_body) => *rigid_body.position(),
+            None => return,
+        };
+
+        let shift_x = if pos.translation.vector.x > 0.0 {
+            -2.0 * max_x
+        } else {
+            2.0 * max_x
+        };
+        let shift_y = if pos.translation.vector.y > 0.0 {
+            -2.0 * max_y
+        } else {
+            2.0 * max_y
+        };
+        let shifts = [
+            Vector2::new(shift_x, 0.0),
+            Vector2::new(0.0, shift_y),
+            Vector2::new(shift_x, shift_y),
+        ];
+
+        for (&ghost_handle, shift) in self.ghost_handles.iter().zip(&shifts) {
+            if let Some(ghost) = colliders.get_mut(ghost_handle) {
+                ghost.set_position(Translation2::from(*shift) * pos);
+            }
+        }
+    }
+
     fn apply_wraparound(&mut self, bodies: &mut DefaultBodySet<f32>, max_x: f32, max_y: f32) {
         let mut modified = false;
 
@@ -148,11 +289,15 @@ impl Physical {
     }
 }
 
-pub struct Bodies(DefaultBodySet<f32>);
+/// All of the bodies, along with a static ground body that ghost colliders are attached to.
+pub struct Bodies(DefaultBodySet<f32>, DefaultBodyHandle);
 
 impl Default for Bodies {
     fn default() -> Self {
-        Bodies(DefaultBodySet::new())
+        let mut bodies = DefaultBodySet::new();
+        let ground_handle = bodies.insert(Ground::new());
+
+        Bodies(bodies, ground_handle)
     }
 }
 
@@ -164,6 +309,69 @@ impl Default for Colliders {
     }
 }
 
+/// Maps each collider back to the entity that owns it, so contacts
+/// reported by the geometrical world can be turned into entity events.
+#[derive(Default)]
+pub struct ColliderEntities(HashMap<DefaultColliderHandle, Entity>);
+
+/// Sent when the colliders of two entities start touching.
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionEvent {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+}
+
+impl CollisionEvent {
+    /// If either side of the collision is `entity`, returns the other side.
+    pub fn other(&self, entity: Entity) -> Option<Entity> {
+        if self.entity_a == entity {
+            Some(self.entity_b)
+        } else if self.entity_b == entity {
+            Some(self.entity_a)
+        } else {
+            None
+        }
+    }
+}
+
+/// Marks an entity for removal. Entities should be destroyed with this
+/// instead of being deleted directly, so that their physics body and
+/// collider are removed along with them.
+#[derive(Component, Debug, Default)]
+#[storage(NullStorage)]
+pub struct Destroy;
+
+/// Makes Physics report everything the entity is already touching, as well as
+/// new contacts. For entities that could not be hurt until now, such as a ship
+/// that stops being invulnerable, or comes out of hyperspace, on top of an asteroid.
+#[derive(Component, Debug, Default)]
+#[storage(NullStorage)]
+pub struct RecheckContacts;
+
+/// A filled collision shape matching the filled rendered shape.
+/// Concave shapes become a compound of convex pieces.
+fn collision_shape(shape: &Shape) -> ShapeHandle<f32> {
+    let mut pieces: Vec<ShapeHandle<f32>> = shape
+        .convex_pieces()
+        .into_iter()
+        .map(|piece| {
+            let polygon = ConvexPolygon::try_new(piece.clone())
+                .or_else(|| ConvexPolygon::try_from_points(&piece))
+                .expect("degenerate collision shape");
+            ShapeHandle::new(polygon)
+        })
+        .collect();
+
+    if pieces.len() == 1 {
+        pieces.pop().unwrap()
+    } else {
+        let identity = Isometry2::identity();
+        ShapeHandle::new(Compound::new(
+            pieces.into_iter().map(|piece| (identity, piece)).collect(),
+        ))
+    }
+}
+
 pub struct CollisionCreator;
 
 impl<'a> System<'a> for CollisionCreator {
@@ -172,17 +380,29 @@ impl<'a> System<'a> for CollisionCreator {
         Read<'a, LazyUpdate>,
         Write<'a, Bodies>,
         Write<'a, Colliders>,
+        Write<'a, ColliderEntities>,
         Entities<'a>,
         ReadStorage<'a, AddCollision>,
         ReadStorage<'a, Shape>,
+        WriteStorage<'a, Physical>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (lazy, mut wrapped_bodies, mut wrapped_colliders, entities, add_collisions, shapes) =
-            data;
+        let (
+            lazy,
+            mut wrapped_bodies,
+            mut wrapped_colliders,
+            mut collider_entities,
+            entities,
+            add_collisions,
+            shapes,
+            mut physicals,
+        ) = data;
+
+        let ground_handle = wrapped_bodies.1;
 
         for (e, add_collision, shape) in (&entities, &add_collisions, &shapes).join() {
-            let shape_handle = ShapeHandle::new(Polyline::new(shape.verts.clone(), None));
+            let shape_handle = collision_shape(shape);
 
             let bodies = &mut wrapped_bodies.0;
             let colliders = &mut wrapped_colliders.0;
@@ -195,16 +415,41 @@ impl<'a> System<'a> for CollisionCreator {
 
             let rigid_body_handle = bodies.insert(rigid_body);
 
-            let collider = ColliderDesc::new(shape_handle)
+            let collider = ColliderDesc::new(shape_handle.clone())
                 .margin(0.002)
                 .build(BodyPartHandle(rigid_body_handle, 0));
-            colliders.insert(collider);
-
+            let collider_handle = colliders.insert(collider);
+            collider_entities.0.insert(collider_handle, e);
+
+            // Ghosts are sensors, so they report overlaps without pushing anything.
+            // They are positioned by Physics before every step.
+            let mut create_ghost = || {
+                let ghost = ColliderDesc::new(shape_handle.clone())
+                    .margin(0.002)
+                    .sensor(true)
+                    .build(BodyPartHandle(ground_handle, 0));
+                let ghost_handle = colliders.insert(ghost);
+                collider_entities.0.insert(ghost_handle, e);
+                ghost_handle
+            };
+            let ghost_handles = [create_ghost(), create_ghost(), create_ghost()];
+
+            // Physical is inserted immediately, rather than lazily, so that an
+            // entity destroyed during its first frame still has its body removed.
             lazy.remove::<AddCollision>(e);
-            lazy.insert(
-                e,
-                Physical::new(add_collision.pos, add_collision.vel, rigid_body_handle),
-            );
+            physicals
+                .insert(
+                    e,
+                    Physical::new(
+                        add_collision.pos,
+                        add_collision.vel,
+                        add_collision.spin,
+                        rigid_body_handle,
+                        collider_handle,
+                        ghost_handles,
+                    ),
+                )
+                .expect("failed to insert Physical");
         }
     }
 }
@@ -213,7 +458,6 @@ pub struct Physics {
     max_x: f32,
     max_y: f32,
     max_speed: f32,
-    extra_frame_time: f32,
 
     joints: DefaultJointConstraintSet<f32>,
     forces: DefaultForceGeneratorSet<f32>,
@@ -232,7 +476,6 @@ impl Physics {
             max_x,
             max_y,
             max_speed,
-            extra_frame_time: 0.0,
 
             joints,
             forces,
@@ -240,46 +483,57 @@ impl Physics {
             mworld: DefaultMechanicalWorld::new(Vector2::zeros()),
         }
     }
-
-    fn calc_step_time(&mut self, frame_time: f32) -> (i32, f32) {
-        let step_time = frame_time + self.extra_frame_time;
-
-        let step_frames = step_time * 60.0;
-
-        // If we are going to execute too many frames, just clamp it.
-        if step_frames > 6.0 {
-            self.extra_frame_time = 0.0;
-            (6, 0.1)
-        } else {
-            let full_frames = f32::floor(step_frames);
-            self.extra_frame_time = (step_frames - full_frames) / 60.0;
-            (full_frames as i32, full_frames / 60.0)
-        }
-    }
 }
 
 impl<'a> System<'a> for Physics {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
         Read<'a, Input>,
+        Write<'a, PhysicsClock>,
         Write<'a, Bodies>,
         Write<'a, Colliders>,
+        Read<'a, ColliderEntities>,
+        Write<'a, EventChannel<CollisionEvent>>,
+        Entities<'a>,
         WriteStorage<'a, Physical>,
+        WriteStorage<'a, RecheckContacts>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (input, mut wrapped_bodies, mut wrapped_colliders, mut physical) = data;
+        let (
+            input,
+            mut clock,
+            mut wrapped_bodies,
+            mut wrapped_colliders,
+            collider_entities,
+            mut collision_events,
+            entities,
+            mut physical,
+            mut rechecks,
+        ) = data;
 
         let bodies = &mut wrapped_bodies.0;
         let colliders = &mut wrapped_colliders.0;
 
-        let (physics_steps, physics_frame_time) = self.calc_step_time(input.frame_time);
+        let physics_steps = clock.advance(input.frame_time);
+        let step_time = clock.step_time();
+        let physics_frame_time = physics_steps as f32 * step_time;
 
         for physical in (&mut physical).join() {
             physical.apply_dynamics(bodies, physics_frame_time, self.max_speed);
             physical.apply_wraparound(bodies, self.max_x, self.max_y);
         }
 
-        for _ in 0..physics_steps {
+        for step in 0..physics_steps {
+            let last_step = step + 1 == physics_steps;
+            for physical in (&mut physical).join() {
+                if last_step {
+                    physical.store_previous(bodies);
+                }
+                physical.apply_rotation(bodies, step_time);
+                physical.update_ghosts(bodies, colliders, self.max_x, self.max_y);
+            }
+
             self.mworld.step(
                 &mut self.gworld,
                 bodies,
@@ -287,11 +541,312 @@ impl<'a> System<'a> for Physics {
                 &mut self.joints,
                 &mut self.forces,
             );
+
+            // Events are cleared at the start of every step, so they
+            // must be collected after each one.
+            for contact_event in self.gworld.contact_events().iter() {
+                if let ContactEvent::Started(handle_a, handle_b) = contact_event {
+                    if let (Some(&entity_a), Some(&entity_b)) = (
+                        collider_entities.0.get(handle_a),
+                        collider_entities.0.get(handle_b),
+                    ) {
+                        collision_events.single_write(CollisionEvent { entity_a, entity_b });
+                    }
+                }
+            }
+
+            // Only ghosts are sensors, so these are overlaps across the wraparound edges.
+            for proximity_event in self.gworld.proximity_events().iter() {
+                if proximity_event.new_status != Proximity::Intersecting
+                    || proximity_event.prev_status == Proximity::Intersecting
+                {
+                    continue;
+                }
+
+                if let (Some(&entity_a), Some(&entity_b)) = (
+                    collider_entities.0.get(&proximity_event.collider1),
+                    collider_entities.0.get(&proximity_event.collider2),
+                ) {
+                    if entity_a != entity_b {
+                        collision_events.single_write(CollisionEvent { entity_a, entity_b });
+                    }
+                }
+            }
         }
 
+        // Contacts that started earlier are not reported again by the steps above.
+        for (entity, physical, _) in (&entities, &physical, &rechecks).join() {
+            let handles = std::iter::once(&physical.collider_handle).chain(&physical.ghost_handles);
+            for &handle in handles {
+                let contacts = self
+                    .gworld
+                    .contacts_with(&*colliders, handle, true)
+                    .into_iter()
+                    .flatten()
+                    .map(|(handle_a, _, handle_b, ..)| (handle_a, handle_b));
+                let proximities = self
+                    .gworld
+                    .proximities_with(&*colliders, handle, true)
+                    .into_iter()
+                    .flatten()
+                    .map(|(handle_a, _, handle_b, ..)| (handle_a, handle_b));
+
+                for (handle_a, handle_b) in contacts.chain(proximities) {
+                    let other_handle = if handle_a == handle {
+                        handle_b
+                    } else {
+                        handle_a
+                    };
+                    if let Some(&other) = collider_entities.0.get(&other_handle) {
+                        if other != entity {
+                            collision_events.single_write(CollisionEvent {
+                                entity_a: entity,
+                                entity_b: other,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        rechecks.clear();
+
+        let blend_factor = clock.blend_factor();
         for physical in (&mut physical).join() {
-            physical.apply_step(bodies, self.extra_frame_time);
+            physical.apply_step(bodies);
             physical.apply_wraparound(bodies, self.max_x, self.max_y);
+            physical.update_render_position(blend_factor, self.max_x, self.max_y);
         }
     }
 }
+
+/// Deletes every entity marked with `Destroy`, removing its body and collider
+/// from the physics world.
+pub struct Destroyer;
+
+impl<'a> System<'a> for Destroyer {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Bodies>,
+        Write<'a, Colliders>,
+        Write<'a, ColliderEntities>,
+        ReadStorage<'a, Destroy>,
+        ReadStorage<'a, Physical>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut wrapped_bodies,
+            mut wrapped_colliders,
+            mut collider_entities,
+            destroys,
+            physicals,
+        ) = data;
+
+        let bodies = &mut wrapped_bodies.0;
+        let colliders = &mut wrapped_colliders.0;
+
+        for (e, _, physical) in (&entities, &destroys, physicals.maybe()).join() {
+            if let Some(physical) = physical {
+                colliders.remove(physical.collider_handle);
+                collider_entities.0.remove(&physical.collider_handle);
+                for ghost_handle in &physical.ghost_handles {
+                    colliders.remove(*ghost_handle);
+                    collider_entities.0.remove(ghost_handle);
+                }
+                bodies.remove(physical.body_handle);
+            }
+
+            entities
+                .delete(e)
+                .expect("failed to delete destroyed entity");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        collision_shape, AddCollision, CollisionCreator, CollisionEvent, Physics, RecheckContacts,
+    };
+    use crate::clock::PhysicsClock;
+    use crate::input::Input;
+    use crate::na::{Isometry2, Point2, Vector2};
+    use crate::shape::Shape;
+    use ncollide2d::query::PointQuery;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use specs::shrev::{EventChannel, ReaderId};
+    use specs::{Builder, Dispatcher, DispatcherBuilder, Entity, World, WorldExt};
+
+    // Twice the signed area of the triangle abc.
+    fn area(a: Point2<f32>, b: Point2<f32>, c: Point2<f32>) -> f32 {
+        (b - a).perp(&(c - a))
+    }
+
+    /// Checks that points are inside the collision shape exactly where they
+    /// are inside one of the rendered triangles.
+    fn assert_collision_matches_triangles(shape: &Shape) {
+        let handle = collision_shape(shape);
+        let query = handle.as_point_query().expect("shape has no point query");
+        let identity = Isometry2::identity();
+
+        let samples = 41;
+        let extent = 1.1 * shape.radius;
+        let edge_tolerance = 1e-3 * shape.radius * shape.radius;
+
+        for i in 0..samples {
+            for j in 0..samples {
+                let point = Point2::new(
+                    extent * (2.0 * i as f32 / (samples - 1) as f32 - 1.0),
+                    extent * (2.0 * j as f32 / (samples - 1) as f32 - 1.0),
+                );
+
+                let mut in_triangle = false;
+                let mut near_edge = false;
+                for triangle in shape.indices.chunks(3) {
+                    let a = shape.verts[triangle[0] as usize];
+                    let b = shape.verts[triangle[1] as usize];
+                    let c = shape.verts[triangle[2] as usize];
+                    let sign = area(a, b, c).signum();
+                    let areas = [
+                        sign * area(a, b, point),
+                        sign * area(b, c, point),
+                        sign * area(c, a, point),
+                    ];
+
+                    in_triangle |= areas.iter().all(|&area| area > 0.0);
+                    near_edge |= areas.iter().any(|area| area.abs() < edge_tolerance);
+                }
+
+                if !near_edge {
+                    assert_eq!(
+                        query.contains_point(&identity, &point),
+                        in_triangle,
+                        "mismatch at {:?}",
+                        point
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn collision_shapes_match_rendered_triangles() {
+        assert_collision_matches_triangles(&Shape::create_ship());
+        assert_collision_matches_triangles(&Shape::create_bullet());
+        assert_collision_matches_triangles(&Shape::create_large_saucer());
+        assert_collision_matches_triangles(&Shape::create_small_saucer());
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            assert_collision_matches_triangles(&Shape::create_asteroid(&mut rng, 0.08));
+        }
+    }
+
+    #[test]
+    fn concave_ship_is_split() {
+        let ship = Shape::create_ship();
+        assert_eq!(ship.convex_pieces().len(), 2);
+        assert_eq!(Shape::create_bullet().convex_pieces().len(), 1);
+        assert_eq!(Shape::create_large_saucer().convex_pieces().len(), 2);
+    }
+
+    /// A world on a 2 by 2 screen, with just the physics systems.
+    fn physics_world() -> (
+        World,
+        Dispatcher<'static, 'static>,
+        ReaderId<CollisionEvent>,
+    ) {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(CollisionCreator, "collision_creator", &[])
+            .with(Physics::new((1.0, 1.0)), "physics", &["collision_creator"])
+            .build();
+        dispatcher.setup(&mut world);
+        world.insert(PhysicsClock::default());
+        world.write_resource::<Input>().frame_time = 1.0 / 60.0;
+
+        let reader = world
+            .write_resource::<EventChannel<CollisionEvent>>()
+            .register_reader();
+
+        (world, dispatcher, reader)
+    }
+
+    fn create_bullet(world: &mut World, x: f32) -> Entity {
+        world
+            .create_entity()
+            .with(Shape::create_bullet())
+            .with(AddCollision::new(
+                Isometry2::new(Vector2::new(x, 0.0), 0.0),
+                Vector2::zeros(),
+            ))
+            .build()
+    }
+
+    /// Steps the world once, and returns the pairs that collided.
+    fn step(
+        world: &mut World,
+        dispatcher: &mut Dispatcher,
+        reader: &mut ReaderId<CollisionEvent>,
+    ) -> Vec<(Entity, Entity)> {
+        dispatcher.dispatch(world);
+        world.maintain();
+
+        world
+            .read_resource::<EventChannel<CollisionEvent>>()
+            .read(reader)
+            .map(|event| (event.entity_a, event.entity_b))
+            .collect()
+    }
+
+    /// Steps two bullets placed at x and -x, and returns the pairs that collided.
+    fn collide_across_seam(x: f32) -> (Entity, Entity, Vec<(Entity, Entity)>) {
+        let (mut world, mut dispatcher, mut reader) = physics_world();
+        let right = create_bullet(&mut world, x);
+        let left = create_bullet(&mut world, -x);
+
+        let mut collisions = Vec::new();
+        for _ in 0..5 {
+            collisions.extend(step(&mut world, &mut dispatcher, &mut reader));
+        }
+
+        (right, left, collisions)
+    }
+
+    #[test]
+    fn collides_across_wraparound_edge() {
+        // Bullets are 0.005 in radius, so these overlap across the edge.
+        let (right, left, collisions) = collide_across_seam(0.997);
+        assert!(collisions
+            .iter()
+            .any(|&pair| pair == (right, left) || pair == (left, right)));
+
+        // These are far enough apart that they do not.
+        let (_, _, collisions) = collide_across_seam(0.98);
+        assert!(collisions.is_empty());
+    }
+
+    #[test]
+    fn recheck_reports_existing_contacts() {
+        let (mut world, mut dispatcher, mut reader) = physics_world();
+        let first = create_bullet(&mut world, 0.0);
+        let second = create_bullet(&mut world, 0.0);
+
+        // Contact is only reported when it starts.
+        assert!(!step(&mut world, &mut dispatcher, &mut reader).is_empty());
+        assert!(step(&mut world, &mut dispatcher, &mut reader).is_empty());
+
+        world
+            .write_storage::<RecheckContacts>()
+            .insert(first, RecheckContacts)
+            .unwrap();
+        assert_eq!(
+            step(&mut world, &mut dispatcher, &mut reader),
+            vec![(first, second)]
+        );
+        assert!(world.read_storage::<RecheckContacts>().is_empty());
+    }
+}
@@ -1,10 +1,18 @@
+use anyhow::{ensure, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use specs::storage::BTreeStorage;
-use specs::{Component, Join, Read, ReadStorage, System, WriteStorage};
+use specs::{Builder, Component, Entities, Join, LazyUpdate, Read, System, Write, WriteStorage};
 
-use crate::na::Vector2;
+use crate::na::{Isometry2, Translation2, Vector2};
 
+use crate::bullet::Bullet;
 use crate::input::Input;
-use crate::physics::Physical;
+use crate::particles::{ParticleEmitter, ParticleParams, Particles};
+use crate::physics::{Bodies, Destroy, Physical, RecheckContacts};
+use crate::prefab::{Overrides, Prefabs};
+use crate::rng::GameRng;
+use crate::sound::{SoundEvent, SoundQueue};
 
 #[derive(Component, Debug)]
 #[storage(BTreeStorage)]
@@ -13,51 +21,323 @@ pub struct Player {
     lateral_acceleration: f32,
 
     angular_acceleration: f32,
+
+    fire_delay: f32,
+    fire_cooldown: f32,
+
+    bullet_speed: f32,
+    bullet_lifetime: f32,
+    // Distance in front of the ship that bullets are spawned, so
+    // they do not start out overlapping the ship.
+    bullet_offset: f32,
+
+    hyperspace_delay: f32,
+    hyperspace_cooldown: f32,
+    // Time taken to fade out, and again to fade back in.
+    hyperspace_fade_time: f32,
+    // Chance of the ship blowing up when it comes out of hyperspace.
+    hyperspace_failure_chance: f32,
+}
+
+/// Tuning values for the player's ship, as given in its prefab.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlayerParams {
+    pub forward_acceleration: f32,
+    pub lateral_acceleration: f32,
+    pub angular_acceleration: f32,
+
+    pub fire_delay: f32,
+    pub bullet_speed: f32,
+    pub bullet_lifetime: f32,
+    pub bullet_offset: f32,
+
+    pub hyperspace_delay: f32,
+    pub hyperspace_fade_time: f32,
+    pub hyperspace_failure_chance: f32,
+}
+
+impl PlayerParams {
+    /// Checks for values that would stop the ship from working, such as a
+    /// zero fade time, which would make the ship invisible in hyperspace forever.
+    pub fn validate(&self) -> Result<()> {
+        let positive = [
+            ("forward_acceleration", self.forward_acceleration),
+            ("lateral_acceleration", self.lateral_acceleration),
+            ("angular_acceleration", self.angular_acceleration),
+            ("fire_delay", self.fire_delay),
+            ("bullet_speed", self.bullet_speed),
+            ("bullet_lifetime", self.bullet_lifetime),
+            ("hyperspace_delay", self.hyperspace_delay),
+            ("hyperspace_fade_time", self.hyperspace_fade_time),
+        ];
+        for &(name, value) in positive.iter() {
+            ensure!(value > 0.0, "{} must be positive", name);
+        }
+
+        ensure!(
+            self.bullet_offset >= 0.0,
+            "bullet_offset must not be negative"
+        );
+        ensure!(
+            (0.0..=1.0).contains(&self.hyperspace_failure_chance),
+            "hyperspace_failure_chance must be between 0 and 1"
+        );
+
+        Ok(())
+    }
 }
 
 impl Player {
-    pub fn new() -> Self {
+    pub fn new(params: &PlayerParams) -> Self {
         Player {
-            forward_acceleration: 0.50 * 10.0,
-            lateral_acceleration: 0.25 * 10.0,
+            forward_acceleration: params.forward_acceleration,
+            lateral_acceleration: params.lateral_acceleration,
+
+            angular_acceleration: params.angular_acceleration,
 
-            angular_acceleration: 2.0 * std::f32::consts::PI,
+            fire_delay: params.fire_delay,
+            fire_cooldown: 0.0,
+
+            bullet_speed: params.bullet_speed,
+            bullet_lifetime: params.bullet_lifetime,
+            bullet_offset: params.bullet_offset,
+
+            hyperspace_delay: params.hyperspace_delay,
+            hyperspace_cooldown: 0.0,
+            hyperspace_fade_time: params.hyperspace_fade_time,
+            hyperspace_failure_chance: params.hyperspace_failure_chance,
+        }
+    }
+}
+
+/// A ship in the middle of a hyperspace jump. It fades out, moves to a
+/// random spot, and fades back in. It can not be controlled meanwhile.
+#[derive(Component, Debug)]
+#[storage(BTreeStorage)]
+pub struct HyperspaceJump {
+    elapsed: f32,
+    fade_time: f32,
+    arrived: bool,
+}
+
+impl HyperspaceJump {
+    fn new(fade_time: f32) -> Self {
+        HyperspaceJump {
+            elapsed: 0.0,
+            fade_time,
+            arrived: false,
         }
     }
+
+    /// How much of the ship to draw, from 0.0 (invisible) to 1.0.
+    pub fn visibility(&self) -> f32 {
+        ((self.elapsed - self.fade_time).abs() / self.fade_time).min(1.0)
+    }
+
+    fn finished(&self) -> bool {
+        self.elapsed >= 2.0 * self.fade_time
+    }
 }
 
-pub struct PlayerController;
+pub struct PlayerController {
+    max_x: f32,
+    max_y: f32,
+
+    ship_explosion: ParticleParams,
+    ship_explosion_particles: u32,
+}
+
+impl PlayerController {
+    pub fn new((max_x, max_y): (f32, f32)) -> Self {
+        PlayerController {
+            max_x,
+            max_y,
+
+            ship_explosion: ParticleParams::explosion(),
+            ship_explosion_particles: 120,
+        }
+    }
+
+    /// Advances a jump, and moves the ship once it has faded out.
+    /// Returns true if the ship blew up on arrival.
+    fn update_jump<R: Rng>(
+        &self,
+        rng: &mut R,
+        bodies: &mut Bodies,
+        frame_time: f32,
+        player: &Player,
+        physical: &mut Physical,
+        jump: &mut HyperspaceJump,
+    ) -> bool {
+        jump.elapsed += frame_time;
+        if jump.arrived || jump.elapsed < jump.fade_time {
+            return false;
+        }
+
+        jump.arrived = true;
+        let pos = Isometry2::from_parts(
+            Translation2::new(
+                rng.gen_range(-self.max_x..self.max_x),
+                rng.gen_range(-self.max_y..self.max_y),
+            ),
+            physical.position().rotation,
+        );
+        physical.teleport(bodies, pos);
+
+        rng.gen::<f32>() < player.hyperspace_failure_chance
+    }
+}
 
 impl<'a> System<'a> for PlayerController {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
         Read<'a, Input>,
-        ReadStorage<'a, Player>,
+        Read<'a, LazyUpdate>,
+        Read<'a, Prefabs>,
+        Write<'a, SoundQueue>,
+        Write<'a, Particles>,
+        Write<'a, GameRng>,
+        Write<'a, Bodies>,
+        Entities<'a>,
+        WriteStorage<'a, Player>,
         WriteStorage<'a, Physical>,
+        WriteStorage<'a, ParticleEmitter>,
+        WriteStorage<'a, HyperspaceJump>,
+        WriteStorage<'a, Destroy>,
+        WriteStorage<'a, RecheckContacts>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (input, player, mut physical) = data;
+        let (
+            input,
+            lazy,
+            prefabs,
+            mut sounds,
+            mut particles,
+            mut rng,
+            mut bodies,
+            entities,
+            mut player,
+            mut physical,
+            mut emitters,
+            mut jumps,
+            mut destroys,
+            mut rechecks,
+        ) = data;
+
+        let mut finished_jumps = Vec::new();
+
+        // Ships without an exhaust emitter still fly, they just leave no trail.
+        for (e, player, physical, mut emitter) in (
+            &entities,
+            &mut player,
+            &mut physical,
+            (&mut emitters).maybe(),
+        )
+            .join()
+        {
+            let actions = &input.actions;
 
-        for (player, physical) in (&player, &mut physical).join() {
-            if input.actions.accel_forward {
-                physical.add_relative_pulse(player.forward_acceleration * Vector2::y());
+            if player.fire_cooldown > 0.0 {
+                player.fire_cooldown -= input.frame_time;
+            }
+            if player.hyperspace_cooldown > 0.0 {
+                player.hyperspace_cooldown -= input.frame_time;
+            }
+
+            if let Some(jump) = jumps.get_mut(e) {
+                if let Some(emitter) = emitter.as_mut() {
+                    emitter.set_intensity(0.0);
+                }
+
+                let exploded = self.update_jump(
+                    &mut *rng,
+                    &mut bodies,
+                    input.frame_time,
+                    player,
+                    physical,
+                    jump,
+                );
+                if exploded {
+                    destroys.insert(e, Destroy).expect("failed to destroy ship");
+                    sounds.push(SoundEvent::ShipExplosion);
+                    particles.burst(
+                        &self.ship_explosion,
+                        self.ship_explosion_particles,
+                        physical.position().translation.vector,
+                    );
+                }
+
+                if jump.finished() {
+                    finished_jumps.push(e);
+                }
+                continue;
+            }
+
+            if actions.hyperspace && player.hyperspace_cooldown <= 0.0 {
+                player.hyperspace_cooldown = player.hyperspace_delay;
+                jumps
+                    .insert(e, HyperspaceJump::new(player.hyperspace_fade_time))
+                    .expect("failed to start hyperspace jump");
+                if let Some(emitter) = emitter.as_mut() {
+                    emitter.set_intensity(0.0);
+                }
+                continue;
             }
 
-            if input.actions.accel_right {
-                physical.add_relative_pulse(player.lateral_acceleration * Vector2::x());
+            let thrust = actions.thrust_amount();
+            if let Some(emitter) = emitter.as_mut() {
+                emitter.set_intensity(thrust);
+            }
+            if thrust > 0.0 {
+                physical.add_relative_pulse(thrust * player.forward_acceleration * Vector2::y());
             }
 
-            if input.actions.accel_left {
-                physical.add_relative_pulse(-player.lateral_acceleration * Vector2::x());
+            let strafe = actions.strafe_amount();
+            if strafe != 0.0 {
+                physical.add_relative_pulse(strafe * player.lateral_acceleration * Vector2::x());
             }
 
-            if input.actions.turn_right {
-                physical.add_angular_pulse(player.angular_acceleration);
+            let turn = actions.turn_amount();
+            if turn != 0.0 {
+                physical.add_angular_pulse(turn * player.angular_acceleration);
             }
 
-            if input.actions.turn_left {
-                physical.add_angular_pulse(-player.angular_acceleration);
+            if actions.fire && player.fire_cooldown <= 0.0 {
+                player.fire_cooldown = player.fire_delay;
+
+                let ship_pos = physical.position();
+                let forward = ship_pos.rotation * Vector2::y();
+
+                let bullet_pos = Isometry2::from_parts(
+                    Translation2::from(
+                        ship_pos.translation.vector + player.bullet_offset * forward,
+                    ),
+                    ship_pos.rotation,
+                );
+                let bullet_vel = physical.velocity() + player.bullet_speed * forward;
+
+                prefabs
+                    .get("bullet")
+                    .expect("bullet prefab is missing")
+                    .apply(
+                        lazy.create_entity(&entities),
+                        Overrides::at(bullet_pos).with_velocity(bullet_vel),
+                    )
+                    .with(Bullet::new(player.bullet_lifetime))
+                    .build();
+
+                sounds.push(SoundEvent::Fire);
             }
         }
+
+        // Collisions are ignored during a jump, so anything the ship
+        // arrived on top of must be found again.
+        for e in finished_jumps {
+            jumps.remove(e);
+            rechecks
+                .insert(e, RecheckContacts)
+                .expect("failed to recheck contacts");
+        }
     }
 }
@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use specs::{Builder, Entity, World, WorldExt};
+
+use crate::na::{Isometry2, Point2, Vector2};
+
+use crate::assets;
+use crate::asteroid::AsteroidSize;
+use crate::color::Color;
+use crate::particles::{ParticleEmitter, ParticleParams};
+use crate::physics::AddCollision;
+use crate::player::{Player, PlayerParams};
+use crate::renderable::Renderable;
+use crate::rng::GameRng;
+use crate::shape::Shape;
+
+const DEFAULT_PREFABS: &str = include_str!("../assets/prefabs.ron");
+
+/// The shape an entity is drawn and collides with.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ShapeParams {
+    Ship,
+    Bullet,
+    LargeSaucer,
+    SmallSaucer,
+    // A jagged outline about scale across, different for each shape seed.
+    Asteroid {
+        scale: f32,
+    },
+    // Points in order around the edge, and triangles made from them.
+    Polygon {
+        verts: Vec<(f32, f32)>,
+        indices: Vec<u16>,
+    },
+}
+
+impl ShapeParams {
+    fn build(&self, seed: u64) -> Shape {
+        match self {
+            ShapeParams::Ship => Shape::create_ship(),
+            ShapeParams::Bullet => Shape::create_bullet(),
+            ShapeParams::LargeSaucer => Shape::create_large_saucer(),
+            ShapeParams::SmallSaucer => Shape::create_small_saucer(),
+            ShapeParams::Asteroid { scale } => {
+                Shape::create_asteroid(&mut GameRng::new(seed), *scale)
+            }
+            ShapeParams::Polygon { verts, indices } => Shape::new(
+                verts.iter().map(|&(x, y)| Point2::new(x, y)).collect(),
+                indices.clone(),
+            ),
+        }
+    }
+
+    /// Checks that the shape can be drawn and collided with.
+    fn validate(&self) -> Result<()> {
+        match self {
+            ShapeParams::Asteroid { scale } => {
+                ensure!(*scale > 0.0, "Asteroid scale must be positive");
+            }
+            ShapeParams::Polygon { verts, indices } => {
+                ensure!(!indices.is_empty(), "Polygon has no triangles");
+                ensure!(
+                    indices.len() % 3 == 0,
+                    "Polygon has {} indices, which is not a whole number of triangles",
+                    indices.len()
+                );
+                if let Some(&index) = indices.iter().find(|&&i| i as usize >= verts.len()) {
+                    bail!(
+                        "Polygon index {} is out of range for {} verts",
+                        index,
+                        verts.len()
+                    );
+                }
+
+                // Only counter-clockwise triangles are drawn, and ones without
+                // area are left out of the collision shape.
+                for (i, triangle) in indices.chunks(3).enumerate() {
+                    let (ax, ay) = verts[triangle[0] as usize];
+                    let (bx, by) = verts[triangle[1] as usize];
+                    let (cx, cy) = verts[triangle[2] as usize];
+                    let twice_area = (bx - ax) * (cy - ay) - (cx - ax) * (by - ay);
+                    ensure!(
+                        twice_area > f32::EPSILON,
+                        "Polygon triangle {} is clockwise or has no area",
+                        i
+                    );
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EmitterParams {
+    particles: ParticleParams,
+    offset: (f32, f32),
+    direction: (f32, f32),
+    rate: f32,
+}
+
+/// The components that make up one kind of entity.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Prefab {
+    shape: ShapeParams,
+    color: Color,
+    #[serde(default)]
+    velocity: (f32, f32),
+    #[serde(default)]
+    spin: f32,
+    #[serde(default)]
+    player: Option<PlayerParams>,
+    #[serde(default)]
+    emitter: Option<EmitterParams>,
+}
+
+impl Prefab {
+    /// The scale of the prefab's shape, if it is an asteroid.
+    pub fn asteroid_scale(&self) -> Option<f32> {
+        match self.shape {
+            ShapeParams::Asteroid { scale } => Some(scale),
+            _ => None,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.shape.validate().context("Bad shape")?;
+        if let Some(ref player) = self.player {
+            player.validate().context("Bad player")?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the prefab's components to the builder.
+    /// Returns the builder, so that more components can be added before it is built.
+    pub fn apply<B: Builder>(&self, builder: B, overrides: Overrides) -> B {
+        let (vel_x, vel_y) = self.velocity;
+        let vel = overrides
+            .velocity
+            .unwrap_or_else(|| Vector2::new(vel_x, vel_y));
+        let spin = overrides.spin.unwrap_or(self.spin);
+
+        let mut builder = builder
+            .with(self.shape.build(overrides.shape_seed))
+            .with(AddCollision::new(overrides.position, vel).with_spin(spin))
+            .with(Renderable::new(self.color));
+
+        if let Some(ref player) = self.player {
+            builder = builder.with(Player::new(player));
+        }
+
+        if let Some(ref emitter) = self.emitter {
+            builder = builder.with(ParticleEmitter::new(
+                emitter.particles.clone(),
+                Vector2::new(emitter.offset.0, emitter.offset.1),
+                Vector2::new(emitter.direction.0, emitter.direction.1),
+                emitter.rate,
+            ));
+        }
+
+        builder
+    }
+}
+
+/// Where a prefab is spawned, and any values that replace the prefab's own.
+#[derive(Clone, Copy, Debug)]
+pub struct Overrides {
+    position: Isometry2<f32>,
+    velocity: Option<Vector2<f32>>,
+    spin: Option<f32>,
+    // Random shapes are made from this, so entities can differ from each other.
+    shape_seed: u64,
+}
+
+impl Overrides {
+    pub fn at(position: Isometry2<f32>) -> Self {
+        Overrides {
+            position,
+            velocity: None,
+            spin: None,
+            shape_seed: 0,
+        }
+    }
+
+    pub fn with_velocity(self, velocity: Vector2<f32>) -> Self {
+        Overrides {
+            velocity: Some(velocity),
+            ..self
+        }
+    }
+
+    pub fn with_spin(self, spin: f32) -> Self {
+        Overrides {
+            spin: Some(spin),
+            ..self
+        }
+    }
+
+    pub fn with_shape_seed(self, shape_seed: u64) -> Self {
+        Overrides { shape_seed, ..self }
+    }
+}
+
+/// Every prefab, by name.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Prefabs {
+    prefabs: BTreeMap<String, Prefab>,
+}
+
+impl Prefabs {
+    /// Loads prefabs.ron. Any built in prefab that it leaves out is kept as it is,
+    /// so the game always has the prefabs it needs.
+    pub fn load() -> Result<Self> {
+        let mut prefabs: Prefabs = assets::load_ron("prefabs.ron")?;
+        prefabs.add_missing(Prefabs::default());
+        prefabs.validate().context("Invalid prefabs.ron")?;
+
+        Ok(prefabs)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (name, prefab) in &self.prefabs {
+            prefab
+                .validate()
+                .with_context(|| format!("Prefab {} is invalid", name))?;
+        }
+
+        // The game relies on these prefabs being what their names say.
+        ensure!(
+            self.get("player")?.player.is_some(),
+            "Prefab player has no player params"
+        );
+        for &size in AsteroidSize::ALL.iter() {
+            let name = size.prefab_name();
+            ensure!(
+                self.get(name)?.asteroid_scale().is_some(),
+                "Prefab {} does not have an Asteroid shape",
+                name
+            );
+        }
+
+        Ok(())
+    }
+
+    /// A hash of every prefab. Replays record it, since a game only plays
+    /// back the same way with the same prefabs.
+    pub fn hash(&self) -> u64 {
+        assets::hash_ron(self)
+    }
+
+    fn add_missing(&mut self, defaults: Prefabs) {
+        for (name, prefab) in defaults.prefabs {
+            self.prefabs.entry(name).or_insert(prefab);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Prefab> {
+        self.prefabs
+            .get(name)
+            .with_context(|| format!("No prefab named {}", name))
+    }
+}
+
+impl Default for Prefabs {
+    fn default() -> Self {
+        ron::from_str(DEFAULT_PREFABS).expect("built in prefabs.ron is invalid")
+    }
+}
+
+/// Creates an entity in world from the named prefab.
+pub fn spawn_prefab(world: &mut World, name: &str, overrides: Overrides) -> Result<Entity> {
+    let prefab = world.read_resource::<Prefabs>().get(name)?.clone();
+
+    Ok(prefab.apply(world.create_entity(), overrides).build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spawn_prefab, Overrides, Prefabs};
+    use crate::na::{Isometry2, Vector2};
+    use crate::particles::ParticleEmitter;
+    use crate::physics::AddCollision;
+    use crate::player::Player;
+    use crate::renderable::Renderable;
+    use crate::shape::Shape;
+    use specs::{World, WorldExt};
+
+    #[test]
+    fn spawns_player_prefab() {
+        let mut world = World::new();
+        world.register::<Shape>();
+        world.register::<AddCollision>();
+        world.register::<Renderable>();
+        world.register::<Player>();
+        world.register::<ParticleEmitter>();
+        world.insert(Prefabs::default());
+
+        let overrides = Overrides::at(Isometry2::identity()).with_velocity(Vector2::new(0.5, 0.0));
+        let player = spawn_prefab(&mut world, "player", overrides).unwrap();
+        assert!(world.read_storage::<Player>().contains(player));
+        assert!(world.read_storage::<ParticleEmitter>().contains(player));
+
+        let bullet = spawn_prefab(&mut world, "bullet", overrides).unwrap();
+        assert!(!world.read_storage::<Player>().contains(bullet));
+
+        assert!(spawn_prefab(&mut world, "no such prefab", overrides).is_err());
+    }
+
+    #[test]
+    fn missing_prefabs_get_defaults() {
+        let mut prefabs: Prefabs = ron::from_str(
+            r#"{
+                "rock": (
+                    shape: Polygon(verts: [(0.0, 0.1), (-0.1, -0.1), (0.1, -0.1)], indices: [0, 1, 2]),
+                    color: (r: 0.5, g: 0.5, b: 0.5, a: 1.0),
+                    spin: 1.0,
+                ),
+            }"#,
+        )
+        .unwrap();
+        prefabs.add_missing(Prefabs::default());
+
+        assert!(prefabs.get("rock").is_ok());
+        assert!(prefabs.get("player").is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_polygons() {
+        let rock = |indices: &str| -> Prefabs {
+            ron::from_str(&format!(
+                r#"{{
+                    "rock": (
+                        shape: Polygon(verts: [(0.0, 0.1), (-0.1, -0.1), (0.1, -0.1)], indices: {}),
+                        color: (r: 0.5, g: 0.5, b: 0.5, a: 1.0),
+                    ),
+                }}"#,
+                indices
+            ))
+            .unwrap()
+        };
+
+        let shape = |prefabs: Prefabs| prefabs.get("rock").unwrap().shape.validate();
+
+        assert!(shape(rock("[0, 1, 2]")).is_ok());
+        assert!(shape(rock("[]")).is_err());
+        assert!(shape(rock("[0, 1]")).is_err());
+        assert!(shape(rock("[0, 1, 3]")).is_err());
+        assert!(shape(rock("[0, 1, 1]")).is_err());
+
+        // Clockwise, so it would never be drawn.
+        assert!(shape(rock("[0, 2, 1]")).is_err());
+        // Each triangle is too thin to collide with, even though together they have area.
+        let slivers: Prefabs = ron::from_str(
+            r#"{
+                "rock": (
+                    shape: Polygon(
+                        verts: [(0.0, 0.0), (1.0, 0.0), (0.0, 1e-7), (1.0, 1e-7), (0.0, 2e-7)],
+                        indices: [0, 1, 2, 2, 1, 3, 2, 3, 4],
+                    ),
+                    color: (r: 0.5, g: 0.5, b: 0.5, a: 1.0),
+                ),
+            }"#,
+        )
+        .unwrap();
+        assert!(shape(slivers).is_err());
+
+        assert!(Prefabs::default().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_player_params() {
+        let mut prefabs = Prefabs::default();
+        let player = prefabs.prefabs.get_mut("player").unwrap();
+
+        player.player.as_mut().unwrap().hyperspace_fade_time = 0.0;
+        assert!(prefabs.validate().is_err());
+
+        prefabs.prefabs.get_mut("player").unwrap().player = None;
+        assert!(prefabs.validate().is_err());
+    }
+}
@@ -0,0 +1,20 @@
+use specs::{Component, VecStorage};
+
+use crate::color::Color;
+
+/// Entities with this component are drawn by the renderer, using their Shape.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct Renderable {
+    color: Color,
+}
+
+impl Renderable {
+    pub fn new(color: Color) -> Self {
+        Renderable { color }
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+}
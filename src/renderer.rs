@@ -1,35 +1,41 @@
+use std::collections::HashMap;
+
+use crate::color::Color;
+use crate::lives::Invulnerable;
 use crate::na;
 use crate::na::{Isometry2, Similarity2, Translation2, Vector2};
+use crate::particles::Particles;
 use crate::physics::Physical;
+use crate::player::HyperspaceJump;
+use crate::renderable::Renderable;
 use crate::shape::Shape;
-use graphics::color::Color;
+use graphics::blend::BlendMode;
+use graphics::color::Color as ScreenColor;
 use graphics::screen::{Screen, ScreenRender};
 use graphics::shape::Shape as ScreenShape;
-use specs::{Component, Join, ReadStorage, VecStorage, WriteStorage};
-
-#[derive(Component, Debug)]
-#[storage(VecStorage)]
-pub struct Renderable {
-    screen_shape: Option<ScreenShape>,
-    color: Color,
-}
-
-impl Renderable {
-    pub fn new(color: Color) -> Self {
-        Renderable {
-            screen_shape: None,
-            color,
-        }
-    }
+use specs::{Entities, Entity, Join, ReadStorage};
+
+/// How ships, asteroids and bullets are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShapeStyle {
+    Filled,
+    // Vector style outlines, this thick.
+    Outline { thickness: f32 },
 }
 
 pub struct Renderer {
     max_x: f32,
     max_y: f32,
+    style: ShapeStyle,
+
+    // Every particle is an instance of this one shape.
+    particle_shape: Option<ScreenShape>,
+    // Each renderable entity's shape, created the first time it is drawn.
+    screen_shapes: HashMap<Entity, ScreenShape>,
 }
 
 impl Renderer {
-    pub fn new(width: f64, height: f64) -> Self {
+    pub fn new(width: f64, height: f64, style: ShapeStyle) -> Self {
         let aspect_ratio = (width / height) as f32;
         let (max_x, max_y) = if aspect_ratio > 1.0 {
             (aspect_ratio, 1.0)
@@ -37,46 +43,92 @@ impl Renderer {
             (1.0, 1.0 / aspect_ratio)
         };
 
-        Self { max_x, max_y }
+        Self {
+            max_x,
+            max_y,
+            style,
+
+            particle_shape: None,
+            screen_shapes: HashMap::new(),
+        }
     }
 
     pub fn get_max_coords(&self) -> (f32, f32) {
         (self.max_x, self.max_y)
     }
 
+    /// Forgets every entity's shape. Must be called when the world is replaced,
+    /// since the new world's entities reuse the old ones' ids.
+    pub fn clear_shapes(&mut self) {
+        self.screen_shapes.clear();
+    }
+
     pub fn update(
-        &self,
+        &mut self,
         screen: &mut Screen,
-        data: (ReadStorage<Shape>, WriteStorage<Renderable>),
+        data: (Entities, ReadStorage<Shape>, ReadStorage<Renderable>),
     ) {
-        let (shapes, mut renderables) = data;
-
-        for (shape, renderable) in (&shapes, &mut renderables).join() {
-            match renderable.screen_shape {
-                Some(_) => (),
-                ref mut s => {
-                    // s is None, so create the shape
-                    let new_s =
-                        screen.create_shape(&shape.verts, &shape.indices, "Renderable Shape");
-                    *s = Some(new_s);
+        let (entities, shapes, renderables) = data;
+
+        if self.particle_shape.is_none() {
+            self.particle_shape = Some(screen.create_circle(1.0, 6, "Particle"));
+        }
+
+        // Forget the shapes of entities that have been deleted.
+        self.screen_shapes.retain(|&e, _| entities.is_alive(e));
+
+        let style = self.style;
+        for (e, shape, _) in (&entities, &shapes, &renderables).join() {
+            self.screen_shapes.entry(e).or_insert_with(|| match style {
+                ShapeStyle::Filled => {
+                    screen.create_shape(&shape.verts, &shape.indices, "Renderable Shape")
                 }
-            };
+                ShapeStyle::Outline { thickness } => {
+                    screen.create_outline_shape(&shape.verts, thickness, "Renderable Outline")
+                }
+            });
         }
     }
 
     pub fn render(
         &self,
-        mut screen_render: ScreenRender,
+        screen_render: &mut ScreenRender,
         data: (
+            Entities,
             ReadStorage<Shape>,
-            WriteStorage<Renderable>,
+            ReadStorage<Renderable>,
             ReadStorage<Physical>,
+            ReadStorage<Invulnerable>,
+            ReadStorage<HyperspaceJump>,
         ),
     ) {
-        let (shapes, mut renderables, physicals) = data;
+        let (entities, shapes, renderables, physicals, invulnerables, jumps) = data;
+
+        for (e, shape, renderable, physical, invulnerable, jump) in (
+            &entities,
+            &shapes,
+            &renderables,
+            &physicals,
+            invulnerables.maybe(),
+            jumps.maybe(),
+        )
+            .join()
+        {
+            if let Some(s) = self.screen_shapes.get(&e) {
+                let mut color = match invulnerable {
+                    Some(invulnerable) if invulnerable.blink_off() => dim(renderable.color()),
+                    _ => renderable.color(),
+                };
+
+                // Ships fade out and back in while jumping through hyperspace.
+                let blend = match jump {
+                    Some(jump) => {
+                        color.a *= jump.visibility();
+                        BlendMode::Alpha
+                    }
+                    None => BlendMode::Opaque,
+                };
 
-        for (shape, renderable, physical) in (&shapes, &mut renderables, &physicals).join() {
-            if let Some(ref s) = renderable.screen_shape {
                 let render_transform = RenderTransform::new(
                     physical.render_position(),
                     self.max_x,
@@ -84,10 +136,42 @@ impl Renderer {
                     shape.radius,
                 );
 
-                render_transform.draw_shape(&mut screen_render, renderable.color, s);
+                render_transform.draw_shape(screen_render, screen_color(color), blend, s);
             }
         }
     }
+
+    /// Draws every particle as a small circle, faded by its age.
+    pub fn render_particles(&self, screen_render: &mut ScreenRender, particles: &Particles) {
+        let particle_shape = match self.particle_shape {
+            Some(ref particle_shape) => particle_shape,
+            None => return,
+        };
+
+        for particle in particles.iter() {
+            let transform = Similarity2::new(particle.position().coords, 0.0, particle.size());
+            screen_render.draw_shape(
+                &transform,
+                screen_color(particle.color()),
+                BlendMode::Additive,
+                particle_shape,
+            );
+        }
+    }
+}
+
+fn screen_color(color: Color) -> ScreenColor {
+    ScreenColor::new(color.r, color.g, color.b, color.a)
+}
+
+fn dim(color: Color) -> Color {
+    let dim_factor = 0.25;
+    Color::new(
+        color.r * dim_factor,
+        color.g * dim_factor,
+        color.b * dim_factor,
+        color.a,
+    )
 }
 
 struct RenderTransform {
@@ -144,10 +228,16 @@ impl RenderTransform {
         RenderTransform { transforms }
     }
 
-    fn draw_shape(&self, screen_render: &mut ScreenRender, color: Color, shape: &ScreenShape) {
+    fn draw_shape(
+        &self,
+        screen_render: &mut ScreenRender,
+        color: ScreenColor,
+        blend: BlendMode,
+        shape: &ScreenShape,
+    ) {
         for optional_transform in &self.transforms {
             if let Some(ref transform) = *optional_transform {
-                screen_render.draw_shape(transform, color, shape);
+                screen_render.draw_shape(transform, color, blend, shape);
             }
         }
     }
@@ -0,0 +1,256 @@
+use anyhow::{bail, ensure, Context, Result};
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+use crate::clock::{PhysicsClock, RenderBlend};
+use crate::input::Actions;
+
+const MAGIC: &[u8; 4] = b"ASTR";
+const VERSION: u8 = 5;
+
+const HEADER_SIZE: usize = 4 + 1 + 8 + 4 + 4 + 1 + 8 + 8 + 4;
+const FRAME_SIZE: usize = 4 + 1;
+const ANALOG_SIZE: usize = 3 * 4;
+
+// Set in a frame's action byte when its proportional controls follow.
+const ANALOG_FLAG: u8 = 1 << 7;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayFrame {
+    pub frame_time: f32,
+    pub actions: Actions,
+}
+
+/// The seed, physics clock and per frame input of a game, which is enough to play it back exactly,
+/// as long as the prefabs and waves have not changed.
+///
+/// The file format is little endian: the magic bytes, a version byte, the u64 seed,
+/// the clock's f32 step rate, u32 max steps and blend byte, the u64 prefab and wave hashes,
+/// a u32 frame count, then each frame's f32 frame time followed by its actions packed into a byte.
+/// If any proportional controls are in use, the f32 thrust, strafe and turn follow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    seed: u64,
+    clock: PhysicsClock,
+    // See Prefabs::hash and WaveTable::hash.
+    prefab_hash: u64,
+    wave_hash: u64,
+    frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, clock: &PhysicsClock, prefab_hash: u64, wave_hash: u64) -> Self {
+        Replay {
+            seed,
+            clock: PhysicsClock::new(clock.step_rate(), clock.max_steps(), clock.blend()),
+            prefab_hash,
+            wave_hash,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The clock the game was recorded with, before any time was added to it.
+    pub fn clock(&self) -> &PhysicsClock {
+        &self.clock
+    }
+
+    pub fn prefab_hash(&self) -> u64 {
+        self.prefab_hash
+    }
+
+    pub fn wave_hash(&self) -> u64 {
+        self.wave_hash
+    }
+
+    pub fn frames(&self) -> &[ReplayFrame] {
+        &self.frames
+    }
+
+    pub fn record(&mut self, frame_time: f32, actions: Actions) {
+        self.frames.push(ReplayFrame {
+            frame_time,
+            actions,
+        });
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Replay::from_bytes(&bytes).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + FRAME_SIZE * self.frames.len());
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.clock.step_rate().to_le_bytes());
+        bytes.extend_from_slice(&self.clock.max_steps().to_le_bytes());
+        bytes.push(match self.clock.blend() {
+            RenderBlend::Interpolate => 0,
+            RenderBlend::Extrapolate => 1,
+        });
+        bytes.extend_from_slice(&self.prefab_hash.to_le_bytes());
+        bytes.extend_from_slice(&self.wave_hash.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+
+        for frame in &self.frames {
+            let actions = &frame.actions;
+            let analog = [actions.thrust, actions.strafe, actions.turn];
+            let has_analog = analog.iter().any(|&value| value != 0.0);
+
+            bytes.extend_from_slice(&frame.frame_time.to_le_bytes());
+            if has_analog {
+                bytes.push(actions.to_bits() | ANALOG_FLAG);
+                for value in &analog {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            } else {
+                bytes.push(actions.to_bits());
+            }
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() >= HEADER_SIZE, "Replay is truncated");
+        ensure!(&bytes[0..4] == MAGIC, "Not a replay file");
+        if bytes[4] != VERSION {
+            bail!("Unsupported replay version {}", bytes[4]);
+        }
+
+        let read_f32 = |at: usize| f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let read_u32 = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+        let seed = u64::from_le_bytes(bytes[5..13].try_into().unwrap());
+        let step_rate = read_f32(13);
+        let max_steps = read_u32(17);
+        let blend = match bytes[21] {
+            0 => RenderBlend::Interpolate,
+            1 => RenderBlend::Extrapolate,
+            other => bail!("Unknown render blend {}", other),
+        };
+        ensure!(
+            step_rate > 0.0 && max_steps > 0,
+            "Replay has an invalid physics clock"
+        );
+        let clock = PhysicsClock::new(step_rate, max_steps, blend);
+        let prefab_hash = u64::from_le_bytes(bytes[22..30].try_into().unwrap());
+        let wave_hash = u64::from_le_bytes(bytes[30..38].try_into().unwrap());
+        let num_frames = read_u32(38) as usize;
+
+        let mut frames = Vec::with_capacity(num_frames.min(bytes.len() / FRAME_SIZE));
+        let mut at = HEADER_SIZE;
+        for _ in 0..num_frames {
+            ensure!(bytes.len() >= at + FRAME_SIZE, "Replay is truncated");
+            let frame_time = read_f32(at);
+            let bits = bytes[at + 4];
+            at += FRAME_SIZE;
+
+            let mut actions = Actions::from_bits(bits & !ANALOG_FLAG);
+            if bits & ANALOG_FLAG != 0 {
+                ensure!(bytes.len() >= at + ANALOG_SIZE, "Replay is truncated");
+                actions.thrust = read_f32(at);
+                actions.strafe = read_f32(at + 4);
+                actions.turn = read_f32(at + 8);
+                at += ANALOG_SIZE;
+            }
+
+            frames.push(ReplayFrame {
+                frame_time,
+                actions,
+            });
+        }
+        ensure!(
+            at == bytes.len(),
+            "Replay has extra data after the last frame"
+        );
+
+        Ok(Replay {
+            seed,
+            clock,
+            prefab_hash,
+            wave_hash,
+            frames,
+        })
+    }
+}
+
+/// Hands out the frames of a replay in order.
+pub struct ReplayPlayer {
+    replay: Replay,
+    next_frame: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer {
+            replay,
+            next_frame: 0,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.replay.seed()
+    }
+
+    pub fn clock(&self) -> &PhysicsClock {
+        self.replay.clock()
+    }
+
+    /// Returns None once every frame has been played.
+    pub fn next_frame(&mut self) -> Option<ReplayFrame> {
+        let frame = self.replay.frames().get(self.next_frame).copied();
+        if frame.is_some() {
+            self.next_frame += 1;
+        }
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Replay;
+    use crate::clock::{PhysicsClock, RenderBlend};
+    use crate::input::Actions;
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let clock = PhysicsClock::new(120.0, 4, RenderBlend::Extrapolate);
+        let mut replay = Replay::new(0x1234_5678_9abc_def0, &clock, 0xfeed_beef, 0xdead_cafe);
+        replay.record(1.0 / 60.0, Actions::default());
+        replay.record(
+            0.02,
+            Actions {
+                accel_forward: true,
+                turn_left: true,
+                fire: true,
+                ..Actions::default()
+            },
+        );
+        replay.record(
+            1.0 / 60.0,
+            Actions {
+                thrust: 0.5,
+                turn: -0.25,
+                ..Actions::default()
+            },
+        );
+
+        let bytes = replay.to_bytes();
+        assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
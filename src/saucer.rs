@@ -0,0 +1,528 @@
+use std::f32;
+
+use rand::Rng;
+use specs::shrev::{EventChannel, ReaderId};
+use specs::storage::BTreeStorage;
+use specs::{
+    Builder, Component, Entities, Entity, Join, LazyUpdate, NullStorage, Read, ReadStorage, System,
+    SystemData, World, Write, WriteStorage,
+};
+
+use crate::na::{Isometry2, Translation2, UnitComplex, Vector2};
+
+use crate::bullet::Bullet;
+use crate::game_state::GameState;
+use crate::input::Input;
+use crate::lives::Invulnerable;
+use crate::particles::{ParticleParams, Particles};
+use crate::physics::{CollisionEvent, Destroy, Physical};
+use crate::player::{HyperspaceJump, Player};
+use crate::prefab::{Overrides, Prefab, Prefabs};
+use crate::rng::GameRng;
+use crate::score::Score;
+use crate::shape::Shape;
+use crate::sound::{SoundEvent, SoundQueue};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaucerSize {
+    Large,
+    Small,
+}
+
+impl SaucerSize {
+    /// Name of the prefab that saucers of this size are built from.
+    pub fn prefab_name(self) -> &'static str {
+        match self {
+            SaucerSize::Large => "large_saucer",
+            SaucerSize::Small => "small_saucer",
+        }
+    }
+
+    /// Points scored for destroying a saucer of this size.
+    pub fn points(self) -> u32 {
+        match self {
+            SaucerSize::Large => 200,
+            SaucerSize::Small => 1000,
+        }
+    }
+
+    pub fn speed(self) -> f32 {
+        match self {
+            SaucerSize::Large => 0.25,
+            SaucerSize::Small => 0.35,
+        }
+    }
+
+    /// Largest angle, in radians, that a shot may miss its aim by.
+    /// Skill runs from 0.0 for a new game to 1.0 for the best aim.
+    pub fn aim_error(self, skill: f32) -> f32 {
+        let (worst, best) = match self {
+            SaucerSize::Large => (0.5, 0.15),
+            SaucerSize::Small => (0.25, 0.0),
+        };
+
+        worst + (best - worst) * skill
+    }
+}
+
+#[derive(Component, Debug)]
+#[storage(BTreeStorage)]
+pub struct Saucer {
+    size: SaucerSize,
+
+    // Points to fly through, in order. The saucer leaves once it reaches the last one.
+    waypoints: Vec<Vector2<f32>>,
+    next_waypoint: usize,
+
+    fire_cooldown: f32,
+}
+
+impl Saucer {
+    pub fn new(size: SaucerSize, waypoints: Vec<Vector2<f32>>, fire_delay: f32) -> Self {
+        Saucer {
+            size,
+            waypoints,
+            next_waypoint: 0,
+            fire_cooldown: fire_delay,
+        }
+    }
+
+    pub fn size(&self) -> SaucerSize {
+        self.size
+    }
+}
+
+/// Bullets fired by saucers. They destroy the player's ship rather than scoring points.
+#[derive(Component, Debug, Default)]
+#[storage(NullStorage)]
+pub struct SaucerBullet;
+
+/// Adds all of the components for a saucer to the builder.
+pub fn build_saucer<B: Builder>(
+    builder: B,
+    prefab: &Prefab,
+    size: SaucerSize,
+    pos: Vector2<f32>,
+    waypoints: Vec<Vector2<f32>>,
+    fire_delay: f32,
+) -> Entity {
+    prefab
+        .apply(builder, Overrides::at(Isometry2::new(pos, 0.0)))
+        .with(Saucer::new(size, waypoints, fire_delay))
+        .build()
+}
+
+/// Direction to fire a bullet at speed so that it meets a target that is at
+/// offset and moving with velocity. None if the bullet can never catch it.
+fn lead_direction(
+    offset: Vector2<f32>,
+    velocity: Vector2<f32>,
+    speed: f32,
+) -> Option<Vector2<f32>> {
+    // Solve |offset + velocity * t| = speed * t for the earliest t > 0.
+    let a = velocity.norm_squared() - speed * speed;
+    let b = 2.0 * offset.dot(&velocity);
+    let c = offset.norm_squared();
+
+    let t = if a.abs() <= f32::EPSILON {
+        -c / b
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        let t0 = (-b - root) / (2.0 * a);
+        let t1 = (-b + root) / (2.0 * a);
+        if t0.min(t1) > 0.0 {
+            t0.min(t1)
+        } else {
+            t0.max(t1)
+        }
+    };
+
+    if t > 0.0 && t.is_finite() {
+        Some((offset + velocity * t).normalize())
+    } else {
+        None
+    }
+}
+
+/// Sends saucers across the screen now and then. Each one flies through its
+/// waypoints and shoots at the player, aiming better as the score goes up.
+pub struct SaucerAi {
+    max_x: f32,
+    max_y: f32,
+
+    spawn_delay: f32,
+    // Time until the next saucer, counted down while there is none.
+    spawn_timer: f32,
+    // Below this score, the chance of a small saucer is in proportion to the score.
+    small_saucer_score: f32,
+    // Saucers aim as well as they can at this score and above.
+    best_aim_score: f32,
+
+    num_waypoints: usize,
+    waypoint_radius: f32,
+    // How quickly saucers turn onto their path, per second.
+    steering: f32,
+
+    fire_delay: f32,
+    bullet_speed: f32,
+    bullet_lifetime: f32,
+}
+
+impl SaucerAi {
+    pub fn new((max_x, max_y): (f32, f32)) -> Self {
+        let spawn_delay = 15.0;
+
+        SaucerAi {
+            max_x,
+            max_y,
+
+            spawn_delay,
+            spawn_timer: spawn_delay,
+            small_saucer_score: 10_000.0,
+            best_aim_score: 40_000.0,
+
+            num_waypoints: 4,
+            waypoint_radius: 0.1,
+            steering: 6.0,
+
+            fire_delay: 1.2,
+            bullet_speed: 0.9,
+            bullet_lifetime: 1.2,
+        }
+    }
+
+    /// The shortest offset from one point to another, which may cross the wraparound edges.
+    fn wrapped_offset(&self, from: Vector2<f32>, to: Vector2<f32>) -> Vector2<f32> {
+        let mut offset = to - from;
+        if offset.x > self.max_x {
+            offset.x -= 2.0 * self.max_x;
+        } else if offset.x < -self.max_x {
+            offset.x += 2.0 * self.max_x;
+        }
+        if offset.y > self.max_y {
+            offset.y -= 2.0 * self.max_y;
+        } else if offset.y < -self.max_y {
+            offset.y += 2.0 * self.max_y;
+        }
+
+        offset
+    }
+
+    fn spawn<R: Rng>(
+        &self,
+        lazy: &LazyUpdate,
+        entities: &Entities,
+        prefabs: &Prefabs,
+        rng: &mut R,
+        score: u32,
+    ) {
+        let small_chance = (score as f32 / self.small_saucer_score).min(1.0);
+        let size = if rng.gen::<f32>() < small_chance {
+            SaucerSize::Small
+        } else {
+            SaucerSize::Large
+        };
+
+        // Enter at one side and leave at the other, wandering up and down on the way.
+        let direction = if rng.gen() { 1.0 } else { -1.0 };
+        let start_x = -direction * self.max_x;
+        let max_y = 0.8 * self.max_y;
+
+        let start = Vector2::new(start_x, rng.gen_range(-max_y..max_y));
+        let waypoints = (1..=self.num_waypoints)
+            .map(|i| {
+                let fraction = i as f32 / self.num_waypoints as f32;
+                Vector2::new(
+                    start_x + direction * 2.0 * self.max_x * fraction,
+                    rng.gen_range(-max_y..max_y),
+                )
+            })
+            .collect();
+
+        build_saucer(
+            lazy.create_entity(entities),
+            prefabs
+                .get(size.prefab_name())
+                .expect("saucer prefab is missing"),
+            size,
+            start,
+            waypoints,
+            self.fire_delay,
+        );
+    }
+
+    /// Picks a direction to shoot from pos at a target. Small saucers lead the target.
+    fn aim<R: Rng>(
+        &self,
+        rng: &mut R,
+        size: SaucerSize,
+        skill: f32,
+        pos: Vector2<f32>,
+        (target_pos, target_vel): (Vector2<f32>, Vector2<f32>),
+    ) -> Vector2<f32> {
+        let offset = self.wrapped_offset(pos, target_pos);
+        let direction = match size {
+            SaucerSize::Large => offset,
+            SaucerSize::Small => {
+                lead_direction(offset, target_vel, self.bullet_speed).unwrap_or(offset)
+            }
+        };
+
+        let max_error = size.aim_error(skill);
+        let angle = direction.y.atan2(direction.x) + rng.gen_range(-max_error..=max_error);
+
+        Vector2::new(angle.cos(), angle.sin())
+    }
+}
+
+impl<'a> System<'a> for SaucerAi {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, Input>,
+        Read<'a, LazyUpdate>,
+        Read<'a, GameState>,
+        Read<'a, Score>,
+        Read<'a, Prefabs>,
+        Write<'a, GameRng>,
+        Write<'a, SoundQueue>,
+        Entities<'a>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, HyperspaceJump>,
+        ReadStorage<'a, Shape>,
+        WriteStorage<'a, Saucer>,
+        WriteStorage<'a, Physical>,
+        WriteStorage<'a, Destroy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            input,
+            lazy,
+            game_state,
+            score,
+            prefabs,
+            mut rng,
+            mut sounds,
+            entities,
+            players,
+            jumps,
+            shapes,
+            mut saucers,
+            mut physicals,
+            mut destroys,
+        ) = data;
+
+        if (&saucers, !&destroys).join().next().is_none() && *game_state == GameState::Playing {
+            self.spawn_timer -= input.frame_time;
+            if self.spawn_timer <= 0.0 {
+                self.spawn_timer = self.spawn_delay;
+                self.spawn(&lazy, &entities, &prefabs, &mut *rng, score.points);
+            }
+        }
+
+        // Ships in hyperspace can not be seen, so they are not shot at.
+        let target = (&players, !&jumps, !&destroys, &physicals)
+            .join()
+            .map(|(_, _, _, physical)| {
+                (physical.position().translation.vector, physical.velocity())
+            })
+            .next();
+        let skill = (score.points as f32 / self.best_aim_score).min(1.0);
+
+        let mut finished = Vec::new();
+        for (e, saucer, physical, shape) in
+            (&entities, &mut saucers, &mut physicals, &shapes).join()
+        {
+            let pos = physical.position().translation.vector;
+
+            if let Some(&waypoint) = saucer.waypoints.get(saucer.next_waypoint) {
+                let offset = self.wrapped_offset(pos, waypoint);
+                if offset.norm() < self.waypoint_radius {
+                    saucer.next_waypoint += 1;
+                } else {
+                    let desired_vel = saucer.size.speed() * offset.normalize();
+                    physical.add_pulse(self.steering * (desired_vel - physical.velocity()));
+                }
+            } else {
+                finished.push(e);
+                continue;
+            }
+
+            saucer.fire_cooldown -= input.frame_time;
+            if let Some(target) = target {
+                if saucer.fire_cooldown <= 0.0 {
+                    saucer.fire_cooldown = self.fire_delay;
+
+                    let direction = self.aim(&mut *rng, saucer.size, skill, pos, target);
+                    // Start the bullet clear of the saucer, so it does not hit it.
+                    let bullet_pos = Isometry2::from_parts(
+                        Translation2::from(pos + (shape.radius + 0.01) * direction),
+                        UnitComplex::rotation_between(&Vector2::y(), &direction),
+                    );
+
+                    prefabs
+                        .get("saucer_bullet")
+                        .expect("saucer_bullet prefab is missing")
+                        .apply(
+                            lazy.create_entity(&entities),
+                            Overrides::at(bullet_pos).with_velocity(self.bullet_speed * direction),
+                        )
+                        .with(Bullet::new(self.bullet_lifetime))
+                        .with(SaucerBullet)
+                        .build();
+
+                    sounds.push(SoundEvent::Fire);
+                }
+            }
+        }
+
+        for e in finished {
+            destroys
+                .insert(e, Destroy)
+                .expect("failed to destroy departing saucer");
+        }
+    }
+}
+
+/// Destroys saucers hit by the player's bullets or ship, and ships hit by saucer bullets.
+pub struct SaucerCollisions {
+    reader: Option<ReaderId<CollisionEvent>>,
+
+    explosion: ParticleParams,
+    explosion_particles: u32,
+}
+
+impl SaucerCollisions {
+    pub fn new() -> Self {
+        SaucerCollisions {
+            reader: None,
+
+            explosion: ParticleParams::explosion(),
+            explosion_particles: 120,
+        }
+    }
+}
+
+impl<'a> System<'a> for SaucerCollisions {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, EventChannel<CollisionEvent>>,
+        Write<'a, Score>,
+        Write<'a, SoundQueue>,
+        Write<'a, Particles>,
+        ReadStorage<'a, Saucer>,
+        ReadStorage<'a, SaucerBullet>,
+        ReadStorage<'a, Bullet>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Invulnerable>,
+        ReadStorage<'a, HyperspaceJump>,
+        ReadStorage<'a, Physical>,
+        WriteStorage<'a, Destroy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            collision_events,
+            mut score,
+            mut sounds,
+            mut particles,
+            saucers,
+            saucer_bullets,
+            bullets,
+            players,
+            invulnerables,
+            jumps,
+            physicals,
+            mut destroys,
+        ) = data;
+
+        // Ships in hyperspace cannot hit or be hit.
+        let is_vulnerable_player =
+            |e: Entity| players.contains(e) && !invulnerables.contains(e) && !jumps.contains(e);
+        let is_player_weapon = |e: Entity| {
+            (bullets.contains(e) && !saucer_bullets.contains(e)) || is_vulnerable_player(e)
+        };
+        let is_hit = |target: Entity, impactor: Entity| {
+            (saucers.contains(target) && is_player_weapon(impactor))
+                || (is_vulnerable_player(target) && saucer_bullets.contains(impactor))
+        };
+
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("SaucerCollisions::setup was not called");
+        let events: Vec<CollisionEvent> = collision_events.read(reader).cloned().collect();
+
+        for event in events {
+            let (target, impactor) = if is_hit(event.entity_a, event.entity_b) {
+                (event.entity_a, event.entity_b)
+            } else if is_hit(event.entity_b, event.entity_a) {
+                (event.entity_b, event.entity_a)
+            } else {
+                continue;
+            };
+
+            if destroys.contains(target) || destroys.contains(impactor) {
+                continue;
+            }
+
+            if let Some(saucer) = saucers.get(target) {
+                score.points += saucer.size().points();
+            }
+
+            for &e in &[target, impactor] {
+                if saucers.contains(e) || players.contains(e) {
+                    sounds.push(SoundEvent::ShipExplosion);
+                    if let Some(physical) = physicals.get(e) {
+                        particles.burst(
+                            &self.explosion,
+                            self.explosion_particles,
+                            physical.position().translation.vector,
+                        );
+                    }
+                }
+
+                destroys
+                    .insert(e, Destroy)
+                    .expect("failed to destroy saucer collision");
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(
+            world
+                .fetch_mut::<EventChannel<CollisionEvent>>()
+                .register_reader(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lead_direction;
+    use crate::na::Vector2;
+
+    #[test]
+    fn lead_meets_moving_target() {
+        let offset = Vector2::new(1.0, 0.0);
+        let velocity = Vector2::new(0.0, 0.5);
+        let speed = 1.0;
+
+        let direction = lead_direction(offset, velocity, speed).unwrap();
+
+        // Find when the bullet has gone as far as the target, then check they are in the same place.
+        let t = (0..50).fold(0.0, |t, _| (offset + velocity * t).norm() / speed);
+        let miss = (offset + velocity * t) - direction * speed * t;
+        assert!(miss.norm() < 1e-4, "missed by {}", miss.norm());
+
+        // Nothing can catch a target that is faster than the bullet and moving away.
+        assert_eq!(lead_direction(offset, Vector2::new(2.0, 0.0), speed), None);
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Points earned in the current game.
+#[derive(Default)]
+pub struct Score {
+    pub points: u32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    scores: Vec<u32>,
+}
+
+impl HighScores {
+    const MAX_SCORES: usize = 10;
+
+    /// Loads the high scores, or returns an empty table if none have been saved yet.
+    pub fn load() -> Result<Self> {
+        let path = high_scores_path()?;
+        if !path.exists() {
+            return Ok(HighScores::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let high_scores = ron::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        Ok(high_scores)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = high_scores_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?;
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Adds score to the table.
+    /// Returns its position in the table, or None if it did not make the cut.
+    /// A score of zero never makes the cut.
+    pub fn add(&mut self, score: u32) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+
+        let rank = self
+            .scores
+            .iter()
+            .position(|&s| score > s)
+            .unwrap_or(self.scores.len());
+
+        if rank < Self::MAX_SCORES {
+            self.scores.insert(rank, score);
+            self.scores.truncate(Self::MAX_SCORES);
+            Some(rank)
+        } else {
+            None
+        }
+    }
+
+    /// Scores from highest to lowest.
+    pub fn scores(&self) -> &[u32] {
+        &self.scores
+    }
+}
+
+fn high_scores_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().context("No data directory for this user")?;
+    Ok(data_dir.join("asteroids").join("high_scores.ron"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HighScores;
+
+    #[test]
+    fn add_keeps_scores_sorted() {
+        let mut high_scores = HighScores::default();
+
+        assert_eq!(high_scores.add(100), Some(0));
+        assert_eq!(high_scores.add(300), Some(0));
+        assert_eq!(high_scores.add(200), Some(1));
+
+        assert_eq!(high_scores.scores(), &[300, 200, 100]);
+    }
+
+    #[test]
+    fn add_ignores_zero() {
+        let mut high_scores = HighScores::default();
+
+        assert_eq!(high_scores.add(0), None);
+        assert!(high_scores.scores().is_empty());
+    }
+
+    #[test]
+    fn add_drops_lowest_when_full() {
+        let mut high_scores = HighScores::default();
+        for i in 1..=10 {
+            high_scores.add(i * 10);
+        }
+
+        // Too low to make the table.
+        assert_eq!(high_scores.add(5), None);
+        assert_eq!(high_scores.scores().len(), 10);
+
+        assert_eq!(high_scores.add(55), Some(5));
+        assert_eq!(high_scores.scores().len(), 10);
+        assert_eq!(high_scores.scores().last(), Some(&20));
+    }
+}
@@ -0,0 +1,24 @@
+use anyhow::{Context, Result};
+use graphics::image::Image;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Saves image as a png in the screenshot directory, and returns where it went.
+pub fn save(image: &Image) -> Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .context("No data directory for this user")?
+        .join("asteroids")
+        .join("screenshots");
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+    let path = dir.join(format!("screenshot-{}.png", millis));
+
+    image.save_png(&path)?;
+
+    Ok(path)
+}
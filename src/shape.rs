@@ -9,6 +9,7 @@ use specs::{Component, VecStorage};
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
 pub struct Shape {
+    // In order around the edge, so they can also be drawn as an outline.
     pub verts: Vec<Point2<f32>>,
     pub indices: Vec<u16>,
     pub radius: f32,
@@ -34,6 +35,34 @@ impl Shape {
         }
     }
 
+    /// Splits the shape into convex polygons, each counter-clockwise.
+    /// The pieces are built by merging the triangles, so they cover
+    /// exactly what is rendered when the shape is filled.
+    pub fn convex_pieces(&self) -> Vec<Vec<Point2<f32>>> {
+        let mut pieces: Vec<Vec<Point2<f32>>> = Vec::new();
+
+        for triangle in self.indices.chunks(3) {
+            let mut triangle: Vec<Point2<f32>> =
+                triangle.iter().map(|&i| self.verts[i as usize]).collect();
+            if triangle.len() < 3 || area(&triangle).abs() <= f32::EPSILON {
+                continue;
+            }
+            if area(&triangle) < 0.0 {
+                triangle.reverse();
+            }
+
+            let merged = pieces
+                .last()
+                .and_then(|piece| merge_triangle(piece, &triangle));
+            match merged {
+                Some(merged) => *pieces.last_mut().unwrap() = merged,
+                None => pieces.push(triangle),
+            }
+        }
+
+        pieces
+    }
+
     pub fn create_ship() -> Self {
         let scale = 0.025;
 
@@ -48,9 +77,46 @@ impl Shape {
         Shape::new(verts, indices)
     }
 
-    pub fn create_asteroid<R: Rng>(mut rng: &mut R) -> Self {
-        let scale = 0.08;
+    pub fn create_bullet() -> Self {
+        let scale = 0.005;
+
+        let verts = vec![
+            Point2::new(0.0, 1.0 * scale),
+            Point2::new(-1.0 * scale, 0.0),
+            Point2::new(0.0, -1.0 * scale),
+            Point2::new(1.0 * scale, 0.0),
+        ];
+        let indices = vec![0, 1, 2, 0, 2, 3];
+
+        Shape::new(verts, indices)
+    }
+
+    pub fn create_large_saucer() -> Self {
+        Shape::create_saucer(0.04)
+    }
+
+    pub fn create_small_saucer() -> Self {
+        Shape::create_saucer(0.02)
+    }
+
+    fn create_saucer(scale: f32) -> Self {
+        let verts = vec![
+            Point2::new(-0.4 * scale, -0.3 * scale),
+            Point2::new(0.4 * scale, -0.3 * scale),
+            Point2::new(1.0 * scale, 0.0),
+            Point2::new(0.4 * scale, 0.3 * scale),
+            Point2::new(0.25 * scale, 0.6 * scale),
+            Point2::new(-0.25 * scale, 0.6 * scale),
+            Point2::new(-0.4 * scale, 0.3 * scale),
+            Point2::new(-1.0 * scale, 0.0),
+        ];
+        // The hull, then the dome on top of it.
+        let indices = vec![0, 1, 2, 0, 2, 3, 0, 3, 6, 0, 6, 7, 3, 4, 5, 3, 5, 6];
 
+        Shape::new(verts, indices)
+    }
+
+    pub fn create_asteroid<R: Rng>(mut rng: &mut R, scale: f32) -> Self {
         let num_points: u16 = 8;
 
         let mut verts = Vec::with_capacity(num_points as usize);
@@ -75,6 +141,52 @@ impl Shape {
     }
 }
 
+/// Twice the signed area of a polygon, positive when counter-clockwise.
+fn area(points: &[Point2<f32>]) -> f32 {
+    (0..points.len())
+        .map(|i| {
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+            a.x * b.y - b.x * a.y
+        })
+        .sum()
+}
+
+/// Adds a counter-clockwise triangle to a convex piece, if they share an
+/// edge and the result is still convex.
+fn merge_triangle(piece: &[Point2<f32>], triangle: &[Point2<f32>]) -> Option<Vec<Point2<f32>>> {
+    for i in 0..piece.len() {
+        let a = piece[i];
+        let b = piece[(i + 1) % piece.len()];
+
+        // The shared edge runs the other way around the triangle.
+        for j in 0..3 {
+            if triangle[j] == b && triangle[(j + 1) % 3] == a {
+                let mut merged = piece.to_vec();
+                merged.insert(i + 1, triangle[(j + 2) % 3]);
+
+                return if is_convex(&merged) {
+                    Some(merged)
+                } else {
+                    None
+                };
+            }
+        }
+    }
+
+    None
+}
+
+fn is_convex(points: &[Point2<f32>]) -> bool {
+    let n = points.len();
+    (0..n).all(|i| {
+        let a = points[i];
+        let b = points[(i + 1) % n];
+        let c = points[(i + 2) % n];
+        (b - a).perp(&(c - b)) > 0.0
+    })
+}
+
 struct Noise {
     base_radius: f32,
     radius_var: Uniform<f32>,
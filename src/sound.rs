@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use audio::{Audio, Clip, LoopId};
+use specs::{Join, Read, ReadStorage, System, Write};
+
+use crate::assets;
+use crate::asteroid::{Asteroid, AsteroidSize};
+use crate::game_state::GameState;
+use crate::input::Input;
+use crate::physics::Destroy;
+use crate::waves::Wave;
+
+/// A one-shot sound requested by the simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEvent {
+    Fire,
+    Explosion(AsteroidSize),
+    ShipExplosion,
+    // The heartbeat alternates between a high and a low beat.
+    Beat { high: bool },
+}
+
+impl SoundEvent {
+    const ALL: [SoundEvent; 7] = [
+        SoundEvent::Fire,
+        SoundEvent::Explosion(AsteroidSize::Large),
+        SoundEvent::Explosion(AsteroidSize::Medium),
+        SoundEvent::Explosion(AsteroidSize::Small),
+        SoundEvent::ShipExplosion,
+        SoundEvent::Beat { high: true },
+        SoundEvent::Beat { high: false },
+    ];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            SoundEvent::Fire => "fire.wav",
+            SoundEvent::Explosion(AsteroidSize::Large) => "explosion_large.wav",
+            SoundEvent::Explosion(AsteroidSize::Medium) => "explosion_medium.wav",
+            SoundEvent::Explosion(AsteroidSize::Small) => "explosion_small.wav",
+            SoundEvent::ShipExplosion => "ship_explosion.wav",
+            SoundEvent::Beat { high: true } => "beat_high.wav",
+            SoundEvent::Beat { high: false } => "beat_low.wav",
+        }
+    }
+
+    fn volume(self) -> f32 {
+        match self {
+            SoundEvent::Fire => 0.4,
+            SoundEvent::Beat { .. } => 0.6,
+            _ => 0.8,
+        }
+    }
+}
+
+/// Sounds requested during a step, waiting to be played.
+#[derive(Default)]
+pub struct SoundQueue {
+    events: Vec<SoundEvent>,
+}
+
+impl SoundQueue {
+    pub fn push(&mut self, event: SoundEvent) {
+        self.events.push(event);
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = SoundEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+/// Plays the background heartbeat, which speeds up the longer a wave lasts.
+pub struct Heartbeat {
+    slowest_interval: f32,
+    fastest_interval: f32,
+    // Time taken to go from the slowest to the fastest beat.
+    speed_up_time: f32,
+
+    wave_number: u32,
+    wave_time: f32,
+    beat_timer: f32,
+    high: bool,
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Heartbeat {
+            slowest_interval: 1.0,
+            fastest_interval: 0.25,
+            speed_up_time: 60.0,
+
+            wave_number: 0,
+            wave_time: 0.0,
+            beat_timer: 0.0,
+            high: true,
+        }
+    }
+
+    fn interval(&self) -> f32 {
+        let fraction = (self.wave_time / self.speed_up_time).min(1.0);
+        self.slowest_interval + fraction * (self.fastest_interval - self.slowest_interval)
+    }
+}
+
+impl<'a> System<'a> for Heartbeat {
+    type SystemData = (
+        Read<'a, Input>,
+        Read<'a, GameState>,
+        Read<'a, Wave>,
+        Write<'a, SoundQueue>,
+        ReadStorage<'a, Asteroid>,
+        ReadStorage<'a, Destroy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (input, state, wave, mut sounds, asteroids, destroys) = data;
+
+        if wave.number != self.wave_number {
+            self.wave_number = wave.number;
+            self.wave_time = 0.0;
+            self.beat_timer = 0.0;
+            self.high = true;
+        }
+
+        // Quiet between waves and once the game is over.
+        if *state != GameState::Playing || (&asteroids, !&destroys).join().next().is_none() {
+            return;
+        }
+
+        self.wave_time += input.frame_time;
+        self.beat_timer -= input.frame_time;
+        if self.beat_timer <= 0.0 {
+            sounds.push(SoundEvent::Beat { high: self.high });
+            self.high = !self.high;
+            self.beat_timer += self.interval();
+        }
+    }
+}
+
+/// Loaded clips for every sound in the game, and the looping thrust sound.
+pub struct SoundBank {
+    audio: Audio,
+    clips: HashMap<SoundEvent, Clip>,
+
+    thrust_clip: Option<Clip>,
+    thrust_loop: Option<LoopId>,
+}
+
+impl SoundBank {
+    /// Loads the clips from the sounds asset directory.
+    /// Missing or broken clips are reported and then stay silent.
+    pub fn load(audio: Audio) -> Self {
+        let sounds_dir = assets::assets_dir().join("sounds");
+        let load_clip = |file_name: &str| match audio.load(sounds_dir.join(file_name)) {
+            Ok(clip) => Some(clip),
+            Err(err) => {
+                eprintln!("{:#}", anyhow::Error::new(err));
+                None
+            }
+        };
+
+        let clips = SoundEvent::ALL
+            .iter()
+            .filter_map(|&event| load_clip(event.file_name()).map(|clip| (event, clip)))
+            .collect();
+        let thrust_clip = load_clip("thrust.wav");
+
+        SoundBank {
+            audio,
+            clips,
+
+            thrust_clip,
+            thrust_loop: None,
+        }
+    }
+
+    pub fn play(&mut self, event: SoundEvent) {
+        if let Some(clip) = self.clips.get(&event) {
+            self.audio.play(clip, event.volume());
+        }
+    }
+
+    /// Plays the thrust loop at a volume matching amount, or stops it when amount is zero.
+    pub fn set_thrust(&mut self, amount: f32) {
+        let volume = 0.5 * amount;
+
+        match (self.thrust_loop, &self.thrust_clip) {
+            (Some(id), _) if amount <= 0.0 => {
+                self.audio.stop_loop(id);
+                self.thrust_loop = None;
+            }
+            (Some(id), _) => self.audio.set_loop_volume(id, volume),
+            (None, Some(clip)) if amount > 0.0 => {
+                self.thrust_loop = Some(self.audio.play_looped(clip, volume));
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Heartbeat, SoundBank, SoundEvent};
+    use audio::Audio;
+
+    #[test]
+    fn heartbeat_speeds_up() {
+        let mut heartbeat = Heartbeat::new();
+        assert_eq!(heartbeat.interval(), heartbeat.slowest_interval);
+
+        heartbeat.wave_time = 2.0 * heartbeat.speed_up_time;
+        assert_eq!(heartbeat.interval(), heartbeat.fastest_interval);
+    }
+
+    #[test]
+    fn sounds_load_and_play_silently() {
+        let mut sounds = SoundBank::load(Audio::null());
+        assert_eq!(sounds.clips.len(), SoundEvent::ALL.len());
+        assert!(sounds.thrust_clip.is_some());
+
+        sounds.play(SoundEvent::Fire);
+        sounds.set_thrust(1.0);
+        assert!(sounds.thrust_loop.is_some());
+        sounds.set_thrust(0.0);
+        assert!(sounds.thrust_loop.is_none());
+    }
+}
@@ -0,0 +1,37 @@
+use crate::game_state::GameState;
+use graphics::events::Key;
+
+/// What should happen in response to a key press.
+/// Keys are handled here rather than in GameState, so that the simulation
+/// does not depend on the graphics crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateChange {
+    Switch(GameState),
+    NewGame,
+    Exit,
+}
+
+/// Returns the change caused by pressing key, if any.
+pub fn handle_key(state: GameState, key: &Key) -> Option<StateChange> {
+    match (state, key) {
+        (GameState::Title, Key::Return) | (GameState::Title, Key::Space) => {
+            Some(StateChange::NewGame)
+        }
+        (GameState::Title, Key::Escape) => Some(StateChange::Exit),
+
+        (GameState::Playing, Key::P) | (GameState::Playing, Key::Escape) => {
+            Some(StateChange::Switch(GameState::Paused))
+        }
+
+        (GameState::Paused, Key::P) | (GameState::Paused, Key::Escape) => {
+            Some(StateChange::Switch(GameState::Playing))
+        }
+        (GameState::Paused, Key::Q) => Some(StateChange::Switch(GameState::Title)),
+
+        (GameState::GameOver, Key::Return) | (GameState::GameOver, Key::Escape) => {
+            Some(StateChange::Switch(GameState::Title))
+        }
+
+        _ => None,
+    }
+}
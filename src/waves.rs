@@ -0,0 +1,284 @@
+use std::f32;
+
+use anyhow::{ensure, Result};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use specs::{Entities, Join, LazyUpdate, Read, ReadStorage, System, Write};
+
+use crate::na::{Isometry2, Vector2};
+
+use crate::assets;
+use crate::asteroid::{build_asteroid, Asteroid, AsteroidSize};
+use crate::input::Input;
+use crate::physics::{Destroy, Physical};
+use crate::player::Player;
+use crate::prefab::Prefabs;
+use crate::rng::GameRng;
+
+const DEFAULT_WAVES: &str = include_str!("../assets/waves.ron");
+
+/// Relative chance of spawning each size of asteroid.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SizeWeights {
+    pub large: f32,
+    pub medium: f32,
+    pub small: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WaveParams {
+    pub count: u32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub sizes: SizeWeights,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WaveTable {
+    waves: Vec<WaveParams>,
+
+    extra_count: u32,
+    extra_speed: f32,
+    max_count: u32,
+}
+
+impl WaveTable {
+    pub fn load() -> Result<Self> {
+        let table: WaveTable = assets::load_ron("waves.ron")?;
+        ensure!(
+            !table.waves.is_empty(),
+            "waves.ron must have at least one wave"
+        );
+
+        Ok(table)
+    }
+
+    /// A hash of the whole table. Replays record it, since a game only plays
+    /// back the same way with the same waves.
+    pub fn hash(&self) -> u64 {
+        assets::hash_ron(self)
+    }
+
+    /// Parameters for the wave at index, counting from zero.
+    /// Waves past the end of the table repeat the last wave, made harder.
+    pub fn wave(&self, index: usize) -> WaveParams {
+        let last_index = self.waves.len() - 1;
+        let mut params = self.waves[index.min(last_index)].clone();
+
+        if index > last_index {
+            let extra_waves = (index - last_index) as u32;
+            // Saturating, since late enough waves would overflow.
+            params.count = params
+                .count
+                .saturating_add(extra_waves.saturating_mul(self.extra_count))
+                .min(self.max_count);
+            params.min_speed += extra_waves as f32 * self.extra_speed;
+            params.max_speed += extra_waves as f32 * self.extra_speed;
+        }
+
+        params
+    }
+}
+
+impl Default for WaveTable {
+    fn default() -> Self {
+        ron::from_str(DEFAULT_WAVES).expect("built in waves.ron is invalid")
+    }
+}
+
+/// The current wave, counting from one. Zero before the first wave starts.
+#[derive(Default)]
+pub struct Wave {
+    pub number: u32,
+}
+
+/// Starts the next wave once every asteroid has been destroyed.
+pub struct WaveManager {
+    table: WaveTable,
+
+    max_x: f32,
+    max_y: f32,
+
+    wave_delay: f32,
+    // Asteroids are not spawned within this distance of the player.
+    safe_radius: f32,
+
+    // Time until the next wave starts, set while waiting.
+    wave_timer: Option<f32>,
+}
+
+impl WaveManager {
+    pub fn new(table: WaveTable, (max_x, max_y): (f32, f32)) -> Self {
+        WaveManager {
+            table,
+
+            max_x,
+            max_y,
+
+            wave_delay: 2.0,
+            safe_radius: 0.5,
+
+            wave_timer: None,
+        }
+    }
+
+    fn spawn_wave<R: Rng>(
+        &self,
+        lazy: &LazyUpdate,
+        entities: &Entities,
+        prefabs: &Prefabs,
+        rng: &mut R,
+        params: &WaveParams,
+        player_pos: Vector2<f32>,
+    ) {
+        let sizes = [
+            AsteroidSize::Large,
+            AsteroidSize::Medium,
+            AsteroidSize::Small,
+        ];
+        let size_index =
+            WeightedIndex::new(&[params.sizes.large, params.sizes.medium, params.sizes.small]).ok();
+
+        for _ in 0..params.count {
+            let size = match size_index {
+                Some(ref size_index) => sizes[size_index.sample(rng)],
+                None => AsteroidSize::Large,
+            };
+
+            let pos = self.random_position(rng, player_pos);
+
+            let direction = rng.gen_range(0.0..2.0 * f32::consts::PI);
+            let speed = if params.max_speed > params.min_speed {
+                rng.gen_range(params.min_speed..params.max_speed)
+            } else {
+                params.min_speed
+            };
+            let vel = speed * Vector2::new(direction.cos(), direction.sin());
+
+            build_asteroid(
+                lazy.create_entity(entities),
+                prefabs,
+                rng,
+                size,
+                Isometry2::new(pos, rng.gen_range(0.0..2.0 * f32::consts::PI)),
+                vel,
+            );
+        }
+    }
+
+    /// A random position on screen that is not too close to the player.
+    fn random_position<R: Rng>(&self, rng: &mut R, player_pos: Vector2<f32>) -> Vector2<f32> {
+        let max_attempts = 20;
+
+        let mut pos = Vector2::new(self.max_x, self.max_y);
+        for _ in 0..max_attempts {
+            pos = Vector2::new(
+                rng.gen_range(-self.max_x..self.max_x),
+                rng.gen_range(-self.max_y..self.max_y),
+            );
+
+            if (pos - player_pos).norm() > self.safe_radius {
+                break;
+            }
+        }
+
+        pos
+    }
+}
+
+impl<'a> System<'a> for WaveManager {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, Input>,
+        Read<'a, LazyUpdate>,
+        Read<'a, Prefabs>,
+        Write<'a, Wave>,
+        Write<'a, GameRng>,
+        Entities<'a>,
+        ReadStorage<'a, Asteroid>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Physical>,
+        ReadStorage<'a, Destroy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            input,
+            lazy,
+            prefabs,
+            mut wave,
+            mut rng,
+            entities,
+            asteroids,
+            players,
+            physicals,
+            destroys,
+        ) = data;
+
+        if (&asteroids, !&destroys).join().next().is_some() {
+            self.wave_timer = None;
+            return;
+        }
+
+        // The first wave starts right away.
+        let wave_delay = if wave.number == 0 {
+            0.0
+        } else {
+            self.wave_delay
+        };
+        let wave_timer = self.wave_timer.get_or_insert(wave_delay);
+        *wave_timer -= input.frame_time;
+        if *wave_timer > 0.0 {
+            return;
+        }
+
+        let player_pos = (&players, &physicals)
+            .join()
+            .next()
+            .map(|(_, physical)| physical.position().translation.vector)
+            .unwrap_or_else(Vector2::zeros);
+
+        let params = self.table.wave(wave.number as usize);
+        self.spawn_wave(&lazy, &entities, &prefabs, &mut *rng, &params, player_pos);
+
+        wave.number += 1;
+        self.wave_timer = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WaveTable;
+
+    #[test]
+    fn default_table_parses() {
+        let table = WaveTable::default();
+        assert!(!table.waves.is_empty());
+    }
+
+    #[test]
+    fn waves_past_table_get_harder() {
+        let table = WaveTable::default();
+        let last_index = table.waves.len() - 1;
+
+        let last = table.wave(last_index);
+        let next = table.wave(last_index + 1);
+
+        assert_eq!(next.count, last.count + table.extra_count);
+        assert!(next.min_speed > last.min_speed);
+        assert!(next.max_speed > last.max_speed);
+
+        let far = table.wave(last_index + 1000);
+        assert_eq!(far.count, table.max_count);
+    }
+
+    #[test]
+    fn huge_wave_numbers_do_not_overflow() {
+        let mut table = WaveTable::default();
+        table.extra_count = u32::MAX;
+
+        let huge = table.wave(u32::MAX as usize);
+        assert_eq!(huge.count, table.max_count);
+    }
+}